@@ -0,0 +1,312 @@
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression, Crc};
+use std::io::{Read, Write};
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// Zip 2.0, the first version with deflate.
+const VERSION: u16 = 20;
+/// Made on unix, so the external attributes hold the file mode.
+const VERSION_MADE_BY: u16 = 3 << 8 | VERSION;
+/// 1980-01-01 00:00:00, the earliest time zip can hold, for every file.
+const DOS_DATE: u16 = 1 << 5 | 1;
+const DOS_TIME: u16 = 0;
+/// A regular file, the type bits of the mode.
+const REGULAR_FILE: u32 = 0o100000;
+/// The most deflate can shrink data by, so sizes claiming more than this are lies.
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+/// A file in a zip archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveFile {
+    /// Path in the archive, with `/` between directories.
+    pub name: String,
+    /// Unix permissions.
+    pub mode: u32,
+    pub bytes: Vec<u8>,
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn get_u16(bytes: &[u8], at: usize) -> Result<u16, String> {
+    bytes
+        .get(at..at + 2)
+        .map(|slice| u16::from_le_bytes([slice[0], slice[1]]))
+        .ok_or_else(|| "the archive is truncated".to_owned())
+}
+
+fn get_u32(bytes: &[u8], at: usize) -> Result<u32, String> {
+    bytes
+        .get(at..at + 4)
+        .map(|slice| u32::from_le_bytes([slice[0], slice[1], slice[2], slice[3]]))
+        .ok_or_else(|| "the archive is truncated".to_owned())
+}
+
+fn size_u32(size: usize, what: &str) -> Result<u32, String> {
+    u32::try_from(size).map_err(|_| format!("{what} is too large for a zip archive"))
+}
+
+/// Writes `files` as a zip archive that only depends on their names, modes and contents: they are
+/// sorted by name and all get the same timestamp.
+pub fn write_zip(files: &[ArchiveFile]) -> Result<Vec<u8>, String> {
+    let mut files = files.iter().collect::<Vec<_>>();
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+
+    for file in &files {
+        let mut crc = Crc::new();
+        crc.update(&file.bytes);
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&file.bytes).map_err(|e| e.to_string())?;
+        let deflated = encoder.finish().map_err(|e| e.to_string())?;
+
+        // keep what doesn't compress as it is
+        let (method, data) = if deflated.len() < file.bytes.len() {
+            (DEFLATED, &deflated)
+        } else {
+            (STORED, &file.bytes)
+        };
+
+        let offset = size_u32(out.len(), "the archive")?;
+        let name_len = u16::try_from(file.name.len())
+            .map_err(|_| format!("{} is too long a name", file.name))?;
+        let compressed = size_u32(data.len(), &file.name)?;
+        let size = size_u32(file.bytes.len(), &file.name)?;
+
+        put_u32(&mut out, LOCAL_HEADER);
+        put_u16(&mut out, VERSION);
+        put_u16(&mut out, 0);
+        put_u16(&mut out, method);
+        put_u16(&mut out, DOS_TIME);
+        put_u16(&mut out, DOS_DATE);
+        put_u32(&mut out, crc.sum());
+        put_u32(&mut out, compressed);
+        put_u32(&mut out, size);
+        put_u16(&mut out, name_len);
+        put_u16(&mut out, 0);
+        out.extend_from_slice(file.name.as_bytes());
+        out.extend_from_slice(data);
+
+        put_u32(&mut central, CENTRAL_HEADER);
+        put_u16(&mut central, VERSION_MADE_BY);
+        put_u16(&mut central, VERSION);
+        put_u16(&mut central, 0);
+        put_u16(&mut central, method);
+        put_u16(&mut central, DOS_TIME);
+        put_u16(&mut central, DOS_DATE);
+        put_u32(&mut central, crc.sum());
+        put_u32(&mut central, compressed);
+        put_u32(&mut central, size);
+        put_u16(&mut central, name_len);
+        // extra field, comment, disk
+        put_u16(&mut central, 0);
+        put_u16(&mut central, 0);
+        put_u16(&mut central, 0);
+        // internal attributes, then the mode in the high half of the external ones
+        put_u16(&mut central, 0);
+        put_u32(&mut central, (REGULAR_FILE | file.mode & 0o777) << 16);
+        put_u32(&mut central, offset);
+        central.extend_from_slice(file.name.as_bytes());
+    }
+
+    let count =
+        u16::try_from(files.len()).map_err(|_| "too many files for a zip archive".to_owned())?;
+    let central_offset = size_u32(out.len(), "the archive")?;
+    let central_size = size_u32(central.len(), "the archive")?;
+    out.extend_from_slice(&central);
+
+    put_u32(&mut out, END_OF_CENTRAL_DIRECTORY);
+    put_u16(&mut out, 0);
+    put_u16(&mut out, 0);
+    put_u16(&mut out, count);
+    put_u16(&mut out, count);
+    put_u32(&mut out, central_size);
+    put_u32(&mut out, central_offset);
+    put_u16(&mut out, 0);
+
+    Ok(out)
+}
+
+/// Reads the files of a zip archive, stored or deflated, checking their checksums.
+pub fn read_zip(bytes: &[u8]) -> Result<Vec<ArchiveFile>, String> {
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|&at| get_u32(bytes, at).is_ok_and(|sig| sig == END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| "not a zip archive".to_owned())?;
+
+    let count = get_u16(bytes, end + 10)? as usize;
+    let mut at = get_u32(bytes, end + 16)? as usize;
+    let mut files = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        if get_u32(bytes, at)? != CENTRAL_HEADER {
+            return Err("broken central directory".to_owned());
+        }
+
+        let method = get_u16(bytes, at + 10)?;
+        let crc = get_u32(bytes, at + 16)?;
+        let compressed = get_u32(bytes, at + 20)? as usize;
+        let size = get_u32(bytes, at + 24)? as usize;
+        let name_len = get_u16(bytes, at + 28)? as usize;
+        let extra_len = get_u16(bytes, at + 30)? as usize;
+        let comment_len = get_u16(bytes, at + 32)? as usize;
+        let mode = get_u32(bytes, at + 38)? >> 16 & 0o777;
+        let local = get_u32(bytes, at + 42)? as usize;
+        let name = bytes
+            .get(at + 46..at + 46 + name_len)
+            .ok_or_else(|| "the archive is truncated".to_owned())?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        at += 46 + name_len + extra_len + comment_len;
+
+        if get_u32(bytes, local)? != LOCAL_HEADER {
+            return Err(format!("broken local header for {name}"));
+        }
+        let start = local
+            + 30
+            + get_u16(bytes, local + 26)? as usize
+            + get_u16(bytes, local + 28)? as usize;
+        let data = bytes
+            .get(start..start + compressed)
+            .ok_or_else(|| "the archive is truncated".to_owned())?;
+
+        let contents = match method {
+            STORED => data.to_vec(),
+            DEFLATED => {
+                if size > compressed.saturating_mul(MAX_DEFLATE_RATIO) {
+                    return Err(format!("{name} is corrupted"));
+                }
+
+                // one byte more than the size is enough to tell the size is wrong
+                let mut contents = Vec::new();
+                DeflateDecoder::new(data)
+                    .take(size as u64 + 1)
+                    .read_to_end(&mut contents)
+                    .map_err(|e| format!("{name}: {e}"))?;
+                contents
+            }
+            _ => return Err(format!("{name} uses an unsupported compression method")),
+        };
+
+        let mut check = Crc::new();
+        check.update(&contents);
+        if contents.len() != size || check.sum() != crc {
+            return Err(format!("{name} is corrupted"));
+        }
+
+        files.push(ArchiveFile {
+            name,
+            mode,
+            bytes: contents,
+        });
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<ArchiveFile> {
+        vec![
+            ArchiveFile {
+                name: "index.js".to_owned(),
+                mode: 0o644,
+                bytes: b"module.exports = {};\n".repeat(100),
+            },
+            ArchiveFile {
+                name: "bin/app".to_owned(),
+                mode: 0o755,
+                bytes: (0..=255).collect(),
+            },
+            ArchiveFile {
+                name: "empty".to_owned(),
+                mode: 0o600,
+                bytes: vec![],
+            },
+        ]
+    }
+
+    /// Offset of the data of the first file in an archive.
+    fn first_data(archive: &[u8]) -> usize {
+        30 + get_u16(archive, 26).unwrap() as usize
+    }
+
+    #[test]
+    fn archives_are_reproducible() {
+        let mut reversed = files();
+        reversed.reverse();
+
+        let archive = write_zip(&files()).unwrap();
+        assert_eq!(archive, write_zip(&files()).unwrap());
+        assert_eq!(archive, write_zip(&reversed).unwrap());
+    }
+
+    #[test]
+    fn archives_read_back() {
+        let archive = write_zip(&files()).unwrap();
+        let read = read_zip(&archive).unwrap();
+
+        let mut expected = files();
+        expected.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(read.len(), expected.len());
+        for (read, expected) in read.iter().zip(&expected) {
+            assert_eq!(read.name, expected.name);
+            assert_eq!(read.mode, expected.mode);
+            assert_eq!(read.bytes, expected.bytes);
+        }
+
+        // the text was deflated, the bytes stored
+        assert_eq!(get_u16(&archive, 8).unwrap(), STORED);
+        assert!(archive.len() < 2100);
+    }
+
+    #[test]
+    fn corrupted_archives_are_rejected() {
+        assert_eq!(read_zip(b"not a zip").unwrap_err(), "not a zip archive");
+
+        let archive = write_zip(&files()).unwrap();
+        let mut flipped = archive.clone();
+        flipped[first_data(&archive)] ^= 1;
+        assert!(read_zip(&flipped).is_err());
+
+        assert!(read_zip(&archive[..archive.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn deflated_sizes_are_bounded() {
+        let archive = write_zip(&[ArchiveFile {
+            name: "zeros".to_owned(),
+            mode: 0o644,
+            bytes: vec![0; 100_000],
+        }])
+        .unwrap();
+        assert_eq!(get_u16(&archive, 8).unwrap(), DEFLATED);
+
+        let central = get_u32(&archive, archive.len() - 6).unwrap() as usize;
+        let with_size = |size: u32| {
+            let mut archive = archive.clone();
+            archive[central + 24..central + 28].copy_from_slice(&size.to_le_bytes());
+            read_zip(&archive)
+        };
+
+        // smaller or larger than what inflates, or more than deflate can hold
+        assert_eq!(with_size(99_999).unwrap_err(), "zeros is corrupted");
+        assert_eq!(with_size(100_001).unwrap_err(), "zeros is corrupted");
+        assert_eq!(with_size(u32::MAX).unwrap_err(), "zeros is corrupted");
+        assert_eq!(with_size(100_000).unwrap()[0].bytes.len(), 100_000);
+    }
+}
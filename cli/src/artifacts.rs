@@ -0,0 +1,151 @@
+use serde::Deserialize;
+use std::{fmt::Display, io::BufRead, path::PathBuf};
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    reason: String,
+    #[serde(default)]
+    target: Option<Target>,
+    #[serde(default)]
+    executable: Option<PathBuf>,
+    #[serde(default)]
+    fresh: bool,
+    #[serde(default)]
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompilerMessage {
+    message: String,
+    level: String,
+    #[serde(default)]
+    spans: Vec<Span>,
+    #[serde(default)]
+    rendered: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Span {
+    file_name: String,
+    line_start: usize,
+    is_primary: bool,
+}
+
+/// A binary reported by cargo's `--message-format=json` output.
+#[derive(Debug, Clone)]
+pub struct BinArtifact {
+    pub name: String,
+    pub executable: PathBuf,
+    /// Whether cargo found it up to date instead of building it.
+    pub fresh: bool,
+}
+
+/// An error reported by rustc, at its primary span when it has one.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// The linker rustc couldn't find, when that's the error.
+    pub fn missing_linker(&self) -> Option<&str> {
+        self.message
+            .strip_prefix("linker `")?
+            .strip_suffix("` not found")
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{file}:{line}: {}", self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// What a build reported with `--message-format=json-diagnostic-rendered-ansi`.
+#[derive(Debug, Default)]
+pub struct BuildMessages {
+    pub artifacts: Vec<BinArtifact>,
+    pub errors: Vec<Diagnostic>,
+}
+
+fn bin_artifact(message: Message) -> Option<BinArtifact> {
+    let target = message.target?;
+    if !target.kind.iter().any(|kind| kind == "bin") {
+        return None;
+    }
+
+    Some(BinArtifact {
+        name: target.name,
+        executable: message.executable?,
+        fresh: message.fresh,
+    })
+}
+
+fn sort_artifacts(artifacts: &mut Vec<BinArtifact>) {
+    artifacts.sort_by(|a, b| a.name.cmp(&b.name));
+    artifacts.dedup_by(|a, b| a.executable == b.executable);
+}
+
+/// Collects the binaries cargo produced, skipping build scripts, examples and libraries.
+pub fn read_bin_artifacts(stdout: &[u8]) -> Vec<BinArtifact> {
+    let mut artifacts = stdout
+        .split(|&byte| byte == b'\n')
+        .filter_map(|line| serde_json::from_slice::<Message>(line).ok())
+        .filter(|message| message.reason == "compiler-artifact")
+        .filter_map(bin_artifact)
+        .collect::<Vec<_>>();
+
+    sort_artifacts(&mut artifacts);
+    artifacts
+}
+
+/// Reads cargo's messages as they come, printing the diagnostics to stderr like cargo would, and
+/// collects the binaries and the errors.
+pub fn read_build_messages(stdout: impl BufRead) -> BuildMessages {
+    let mut messages = BuildMessages::default();
+
+    for line in stdout.lines().map_while(Result::ok) {
+        let Ok(message) = serde_json::from_str::<Message>(&line) else {
+            continue;
+        };
+
+        match message.reason.as_str() {
+            "compiler-artifact" => messages.artifacts.extend(bin_artifact(message)),
+            "compiler-message" => {
+                let Some(compiler_message) = message.message else {
+                    continue;
+                };
+
+                if let Some(rendered) = &compiler_message.rendered {
+                    eprint!("{rendered}");
+                }
+
+                if compiler_message.level != "error" {
+                    continue;
+                }
+
+                let span = compiler_message.spans.iter().find(|span| span.is_primary);
+                messages.errors.push(Diagnostic {
+                    file: span.map(|span| span.file_name.clone()),
+                    line: span.map(|span| span.line_start),
+                    message: compiler_message.message,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    sort_artifacts(&mut messages.artifacts);
+    messages
+}
@@ -0,0 +1,289 @@
+use crate::artifacts::BinArtifact;
+use object::Object;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Name of the build manifest in `target/genezio/out`.
+pub const FILE_NAME: &str = "build-manifest.json";
+
+/// What `genezio-rs build` packaged and where it came from, so what gets deployed can be checked
+/// against the build that produced it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuildManifest {
+    pub git_commit: Option<String>,
+    /// Whether the working tree had changes that aren't committed.
+    pub git_dirty: bool,
+    pub target: String,
+    pub profile: String,
+    /// When the build ran, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub binaries: Vec<BuiltBinary>,
+    /// The sha256 of every file in `target/genezio/out` but this one, by path.
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuiltBinary {
+    pub name: String,
+    /// Where cargo wrote it.
+    pub path: PathBuf,
+    pub sha256: String,
+    /// When cargo wrote it, in milliseconds since the Unix epoch.
+    pub modified: u64,
+    /// Its GNU build id, which names its debug info in `target/genezio/symbols`.
+    #[serde(default)]
+    pub build_id: Option<String>,
+}
+
+pub fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// The GNU build id of an ELF binary, in hex.
+pub fn build_id(bytes: &[u8]) -> Option<String> {
+    let file = object::File::parse(bytes).ok()?;
+    let id = file.build_id().ok()??;
+
+    Some(id.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn git(root: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Adds the files under `dir` to `files`, with their path relative to `root`.
+fn hash_files(root: &Path, dir: &Path, files: &mut BTreeMap<String, String>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_dir() {
+            hash_files(root, &path, files)?;
+            continue;
+        }
+
+        let name = path
+            .strip_prefix(root)
+            .map_err(|e| e.to_string())?
+            .to_string_lossy()
+            .into_owned();
+        if name == FILE_NAME {
+            continue;
+        }
+
+        let bytes = fs::read(&path).map_err(|e| e.to_string())?;
+        files.insert(name, sha256(&bytes));
+    }
+
+    Ok(())
+}
+
+impl BuiltBinary {
+    /// Fingerprints a binary cargo reported. Binaries cargo rebuilt must be newer than `started`,
+    /// the time the build started.
+    pub fn read(artifact: &BinArtifact, started: SystemTime) -> Result<Self, String> {
+        let modified = fs::metadata(&artifact.executable)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| format!("{}: {e}", artifact.executable.display()))?;
+
+        if !artifact.fresh && modified < started {
+            return Err(format!(
+                "{} is older than the build that just ran",
+                artifact.executable.display()
+            ));
+        }
+
+        let bytes = fs::read(&artifact.executable)
+            .map_err(|e| format!("{}: {e}", artifact.executable.display()))?;
+
+        Ok(Self {
+            name: artifact.name.clone(),
+            path: artifact.executable.clone(),
+            sha256: sha256(&bytes),
+            modified: millis(modified),
+            build_id: build_id(&bytes),
+        })
+    }
+
+    /// Checks `bytes`, read from the binary, are still what the build produced.
+    pub fn check(&self, bytes: &[u8]) -> Result<(), String> {
+        if sha256(bytes) != self.sha256 {
+            return Err(format!(
+                "{} changed since the build that just ran",
+                self.path.display()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl BuildManifest {
+    pub fn new(workspace_root: &Path, target: String, profile: String) -> Self {
+        Self {
+            git_commit: git(workspace_root, &["rev-parse", "HEAD"]),
+            git_dirty: git(workspace_root, &["status", "--porcelain"])
+                .is_some_and(|status| !status.is_empty()),
+            target,
+            profile,
+            timestamp: millis(SystemTime::now()) / 1000,
+            binaries: vec![],
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn binary(&self, name: &str) -> Option<&BuiltBinary> {
+        self.binaries.iter().find(|binary| binary.name == name)
+    }
+
+    pub fn read(out_dir: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(out_dir.join(FILE_NAME)).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    }
+
+    /// Hashes what the build left in `out_dir` and writes the manifest next to it.
+    pub fn write(&mut self, out_dir: &Path) -> Result<(), String> {
+        self.files.clear();
+        hash_files(out_dir, out_dir, &mut self.files)?;
+
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(out_dir.join(FILE_NAME), text).map_err(|e| e.to_string())
+    }
+
+    /// Checks `out_dir` holds exactly the files the build wrote.
+    pub fn verify(&self, out_dir: &Path) -> Result<(), String> {
+        let mut files = BTreeMap::new();
+        hash_files(out_dir, out_dir, &mut files)?;
+
+        for (name, hash) in &self.files {
+            match files.remove(name) {
+                Some(found) if &found == hash => {}
+                Some(_) => return Err(format!("{name} changed after the build")),
+                None => return Err(format!("{name} is missing")),
+            }
+        }
+
+        if let Some(name) = files.keys().next() {
+            return Err(format!("{name} was not written by the build"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("genezio-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn artifact(executable: PathBuf, fresh: bool) -> BinArtifact {
+        BinArtifact {
+            name: "app".to_owned(),
+            executable,
+            fresh,
+        }
+    }
+
+    #[test]
+    fn binaries_must_be_newer_than_the_build_unless_fresh() {
+        let dir = temp_dir("built-binary");
+        let path = dir.join("app");
+        fs::write(&path, b"binary").unwrap();
+
+        let before = SystemTime::now() - Duration::from_secs(60);
+        let after = SystemTime::now() + Duration::from_secs(60);
+
+        let binary = BuiltBinary::read(&artifact(path.clone(), false), before).unwrap();
+        assert_eq!(binary.sha256, sha256(b"binary"));
+        assert_eq!(binary.path, path);
+        assert_eq!(binary.build_id, None);
+
+        let err = BuiltBinary::read(&artifact(path.clone(), false), after).unwrap_err();
+        assert!(
+            err.ends_with("is older than the build that just ran"),
+            "{err}"
+        );
+        // cargo didn't need to build it again
+        BuiltBinary::read(&artifact(path.clone(), true), after).unwrap();
+
+        binary.check(b"binary").unwrap();
+        assert!(binary.check(b"changed").is_err());
+
+        assert!(BuiltBinary::read(&artifact(dir.join("missing"), true), before).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn verify_finds_changed_missing_and_extra_files() {
+        let dir = temp_dir("build-manifest");
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        fs::write(dir.join("index.js"), "index").unwrap();
+        fs::write(dir.join("bin/app"), "app").unwrap();
+
+        let mut manifest = BuildManifest::new(
+            &dir,
+            "aarch64-unknown-linux-musl".to_owned(),
+            "release".to_owned(),
+        );
+        manifest.write(&dir).unwrap();
+        assert_eq!(
+            manifest.files.keys().collect::<Vec<_>>(),
+            ["bin/app", "index.js"]
+        );
+
+        let manifest = BuildManifest::read(&dir).unwrap();
+        manifest.verify(&dir).unwrap();
+
+        fs::write(dir.join("index.js"), "changed").unwrap();
+        assert_eq!(
+            manifest.verify(&dir).unwrap_err(),
+            "index.js changed after the build"
+        );
+        fs::write(dir.join("index.js"), "index").unwrap();
+
+        fs::remove_file(dir.join("bin/app")).unwrap();
+        assert_eq!(manifest.verify(&dir).unwrap_err(), "bin/app is missing");
+        fs::write(dir.join("bin/app"), "app").unwrap();
+
+        fs::write(dir.join("extra.js"), "extra").unwrap();
+        assert_eq!(
+            manifest.verify(&dir).unwrap_err(),
+            "extra.js was not written by the build"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use toml::{Table, Value};
+
+/// The cargo configuration files a build run from some directory reads, the way cargo finds them:
+/// `.cargo/config.toml` in the directory and all its parents, then the one in `CARGO_HOME`.
+#[derive(Debug, Default)]
+pub struct CargoConfig {
+    /// From the most to the least specific.
+    files: Vec<Table>,
+}
+
+/// The config file in `dir`, cargo prefers the one without the extension when both are there.
+fn config_file(dir: &Path) -> Option<PathBuf> {
+    ["config", "config.toml"]
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")))
+}
+
+/// A list of strings from the config, which can also be written as a single string.
+fn string_list(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(text) => text.split_whitespace().map(str::to_owned).collect(),
+        Value::Array(values) => values
+            .iter()
+            .filter_map(|value| value.as_str())
+            .map(str::to_owned)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// The name cargo reads a config key of `triple` from in the environment.
+fn target_env(triple: &str, key: &str) -> String {
+    format!(
+        "CARGO_TARGET_{}_{key}",
+        triple.to_uppercase().replace(['-', '.'], "_")
+    )
+}
+
+/// The flags in `CARGO_ENCODED_RUSTFLAGS` or `RUSTFLAGS`. When either is set, cargo uses it instead
+/// of every rustflags in the config files.
+pub fn env_rustflags() -> Option<Vec<String>> {
+    if let Ok(encoded) = env::var("CARGO_ENCODED_RUSTFLAGS") {
+        if encoded.is_empty() {
+            return Some(vec![]);
+        }
+
+        return Some(encoded.split('\x1f').map(str::to_owned).collect());
+    }
+
+    env::var("RUSTFLAGS")
+        .ok()
+        .map(|flags| flags.split_whitespace().map(str::to_owned).collect())
+}
+
+/// The triple rustc builds for when no `--target` is passed.
+pub fn host_triple() -> Result<String, String> {
+    let output = Command::new("rustc")
+        .arg("-vV")
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(str::to_owned)
+        .ok_or_else(|| "rustc did not print the host triple".to_owned())
+}
+
+impl CargoConfig {
+    pub fn read(dir: &Path) -> Result<Self, String> {
+        let mut paths = dir
+            .ancestors()
+            .filter_map(|dir| config_file(&dir.join(".cargo")))
+            .collect::<Vec<_>>();
+
+        if let Some(path) = cargo_home().and_then(|home| config_file(&home)) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+
+        let files = paths
+            .iter()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| text.parse::<Table>().map_err(|e| e.to_string()))
+                    .map_err(|e| format!("{}: {e}", path.display()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { files })
+    }
+
+    /// The value of `keys` in every file that has it, from the most to the least specific.
+    fn values<'a>(&'a self, keys: &'a [&'a str]) -> impl DoubleEndedIterator<Item = &'a Value> {
+        self.files.iter().filter_map(move |file| {
+            let (last, tables) = keys.split_last()?;
+            let mut table = file;
+            for key in tables {
+                table = table.get(*key)?.as_table()?;
+            }
+            table.get(*last)
+        })
+    }
+
+    /// `build.rustflags`, joined across files the way cargo joins arrays.
+    pub fn build_rustflags(&self) -> Vec<String> {
+        let mut flags = self
+            .values(&["build", "rustflags"])
+            .rev()
+            .flat_map(string_list)
+            .collect::<Vec<_>>();
+
+        if let Ok(env_flags) = env::var("CARGO_BUILD_RUSTFLAGS") {
+            flags.extend(env_flags.split_whitespace().map(str::to_owned));
+        }
+
+        flags
+    }
+
+    /// Whether `target.<triple>.rustflags` is set, cargo ignores `build.rustflags` then. The
+    /// `target.'cfg(...)'` tables aren't evaluated.
+    pub fn has_target_rustflags(&self, triple: &str) -> bool {
+        env::var_os(target_env(triple, "RUSTFLAGS")).is_some()
+            || self
+                .values(&["target", triple, "rustflags"])
+                .next()
+                .is_some()
+    }
+
+    /// `target.<triple>.linker`.
+    pub fn linker(&self, triple: &str) -> Option<String> {
+        if let Ok(linker) = env::var(target_env(triple, "LINKER")) {
+            return Some(linker);
+        }
+
+        self.values(&["target", triple, "linker"])
+            .find_map(|value| value.as_str())
+            .map(str::to_owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Held by the tests that read or set environment variables.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    const TRIPLE: &str = "aarch64-unknown-linux-musl";
+
+    fn config(files: &[&str]) -> CargoConfig {
+        CargoConfig {
+            files: files.iter().map(|text| text.parse().unwrap()).collect(),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("genezio-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn string_lists() {
+        let table = r#"
+            text = "-C  target-cpu=native"
+            list = ["-C", "opt-level=3", 1]
+            number = 1
+        "#
+        .parse::<Table>()
+        .unwrap();
+
+        assert_eq!(string_list(&table["text"]), ["-C", "target-cpu=native"]);
+        assert_eq!(string_list(&table["list"]), ["-C", "opt-level=3"]);
+        assert!(string_list(&table["number"]).is_empty());
+    }
+
+    #[test]
+    fn build_rustflags_join_the_least_specific_first() {
+        let _env = ENV.lock().unwrap();
+        env::remove_var("CARGO_BUILD_RUSTFLAGS");
+
+        let config = config(&[
+            r#"build.rustflags = ["--cfg", "project"]"#,
+            "[target.x86_64-unknown-linux-gnu]\nlinker = \"cc\"",
+            r#"build.rustflags = "--cfg home""#,
+        ]);
+        assert_eq!(
+            config.build_rustflags(),
+            ["--cfg", "home", "--cfg", "project"]
+        );
+
+        env::set_var("CARGO_BUILD_RUSTFLAGS", "--cfg env");
+        assert_eq!(
+            config.build_rustflags(),
+            ["--cfg", "home", "--cfg", "project", "--cfg", "env"]
+        );
+        env::remove_var("CARGO_BUILD_RUSTFLAGS");
+    }
+
+    #[test]
+    fn target_rustflags() {
+        let _env = ENV.lock().unwrap();
+        let variable = target_env(TRIPLE, "RUSTFLAGS");
+        assert_eq!(
+            variable,
+            "CARGO_TARGET_AARCH64_UNKNOWN_LINUX_MUSL_RUSTFLAGS"
+        );
+        env::remove_var(&variable);
+
+        let build_only = config(&[r#"build.rustflags = ["--cfg", "a"]"#]);
+        assert!(!build_only.has_target_rustflags(TRIPLE));
+
+        let with_target = config(&[
+            r#"build.rustflags = ["--cfg", "a"]"#,
+            &format!("[target.{TRIPLE}]\nrustflags = [\"--cfg\", \"b\"]"),
+        ]);
+        assert!(with_target.has_target_rustflags(TRIPLE));
+        assert!(!with_target.has_target_rustflags("x86_64-unknown-linux-musl"));
+
+        env::set_var(&variable, "--cfg c");
+        assert!(build_only.has_target_rustflags(TRIPLE));
+        env::remove_var(&variable);
+    }
+
+    #[test]
+    fn environment_rustflags() {
+        let _env = ENV.lock().unwrap();
+        env::remove_var("CARGO_ENCODED_RUSTFLAGS");
+        env::remove_var("RUSTFLAGS");
+        assert_eq!(env_rustflags(), None);
+
+        env::set_var("RUSTFLAGS", " --cfg  a ");
+        assert_eq!(env_rustflags().unwrap(), ["--cfg", "a"]);
+
+        // the encoded flags win, and can hold spaces
+        env::set_var("CARGO_ENCODED_RUSTFLAGS", "--cfg\x1ffeature=\"a b\"");
+        assert_eq!(env_rustflags().unwrap(), ["--cfg", "feature=\"a b\""]);
+
+        env::set_var("CARGO_ENCODED_RUSTFLAGS", "");
+        assert_eq!(env_rustflags().unwrap(), Vec::<String>::new());
+
+        env::remove_var("CARGO_ENCODED_RUSTFLAGS");
+        env::remove_var("RUSTFLAGS");
+    }
+
+    #[test]
+    fn config_files_are_found_like_cargo_does() {
+        let _env = ENV.lock().unwrap();
+        let root = temp_dir("cargo-config");
+        let project = root.join("project");
+        fs::create_dir_all(project.join(".cargo")).unwrap();
+        fs::create_dir_all(root.join(".cargo")).unwrap();
+
+        fs::write(
+            project.join(".cargo/config.toml"),
+            "build.rustflags = \"project\"",
+        )
+        .unwrap();
+        // `config` wins over `config.toml` in the same directory
+        fs::write(root.join(".cargo/config"), "build.rustflags = \"root\"").unwrap();
+        fs::write(
+            root.join(".cargo/config.toml"),
+            "build.rustflags = \"ignored\"",
+        )
+        .unwrap();
+
+        // CARGO_HOME is one of the parents, it is only read once
+        let home = env::var_os("CARGO_HOME");
+        env::set_var("CARGO_HOME", root.join(".cargo"));
+        let config = CargoConfig::read(&project);
+        match home {
+            Some(home) => env::set_var("CARGO_HOME", home),
+            None => env::remove_var("CARGO_HOME"),
+        }
+        fs::remove_dir_all(&root).unwrap();
+
+        let config = config.unwrap();
+        assert_eq!(config.files.len(), 2);
+        env::remove_var("CARGO_BUILD_RUSTFLAGS");
+        assert_eq!(config.build_rustflags(), ["root", "project"]);
+    }
+}
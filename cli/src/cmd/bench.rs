@@ -0,0 +1,332 @@
+use crate::{
+    emulator::{function_url_event, read_function_url_response, Emulator, HttpRequest, Outcome},
+    metadata::get_cargo_metadata,
+    options::GlobalOptions,
+};
+use clap::{Args, Subcommand};
+use serde::Deserialize;
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::RecvTimeoutError,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::build::{cargo_build_host_lambda, BuildError};
+
+/// Prefix of the lines printed by `genezio::cold_start` and the generated JavaScript class.
+const COLD_START_PREFIX: &str = "genezio:cold-start ";
+
+/// The phases of a cold start, in the order they happen. The first two are recorded by the
+/// JavaScript class on genezio and by this command when benchmarking, the others by the binary.
+const PHASES: &[&str] = &[
+    "binary_written",
+    "process_spawned",
+    "runtime_built",
+    "router_built",
+    "first_request_served",
+    "response_received",
+];
+
+const PERCENTILES: &[f64] = &[50.0, 90.0, 99.0];
+
+#[derive(Debug, Args)]
+pub struct BenchArgs {
+    #[clap(subcommand)]
+    pub command: BenchCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BenchCommand {
+    /// Cold start the app under a local Lambda emulator several times and report how long each
+    /// phase takes. The binary is started directly, the way `--runtime trap` does, so the time
+    /// Node takes to load `index.js` and forward the request over the bridge socket isn't measured
+    ColdStart(ColdStartArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ColdStartArgs {
+    /// How many cold starts to measure
+    #[clap(long, short = 'n', default_value_t = 10)]
+    pub runs: usize,
+
+    /// Package to benchmark, for workspaces with several packages
+    #[clap(long, short = 'p')]
+    pub package: Option<String>,
+
+    /// Binary to benchmark, for packages with several binaries
+    #[clap(long)]
+    pub bin: Option<String>,
+
+    /// Binary built for Lambda to start instead of building one for the host, like the packaged
+    /// one when running on an aarch64 Linux machine
+    #[clap(long)]
+    pub artifact: Option<PathBuf>,
+
+    /// Path of the request sent to the app
+    #[clap(long, default_value = "/")]
+    pub path: String,
+
+    /// How long to wait for the first response of each run, in milliseconds
+    #[clap(long, default_value_t = 10000)]
+    pub timeout: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhaseLine {
+    phase: String,
+    ms: f64,
+}
+
+/// Milliseconds since the start of a run for every phase that was reported.
+type Sample = Vec<(String, f64)>;
+
+fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+        * 1000.0
+}
+
+/// Collects the phases the binary prints, ignoring everything else it writes.
+fn read_phases(stdout: impl Read + Send + 'static) -> thread::JoinHandle<Vec<PhaseLine>> {
+    thread::spawn(move || {
+        BufReader::new(stdout)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| {
+                serde_json::from_str(line.strip_prefix(COLD_START_PREFIX)?.trim()).ok()
+            })
+            .collect()
+    })
+}
+
+/// Runs a single cold start the way the trap runtime does: writes the binary, starts it and
+/// sends it one request.
+fn cold_start(
+    args: &ColdStartArgs,
+    artifact: &Path,
+    trap: &Path,
+) -> Result<(Sample, u16), BenchError> {
+    let emulator = Emulator::start().map_err(|e| BenchError::Emulator(e.to_string()))?;
+    let request = HttpRequest {
+        method: "GET".to_owned(),
+        path: args.path.clone(),
+        headers: vec![("user-agent".to_owned(), "genezio-rs bench".to_owned())],
+        body: vec![],
+    };
+    let outcome = emulator.invoke(function_url_event(&request, "127.0.0.1"));
+
+    let start = now_ms();
+    let mut sample = Sample::new();
+
+    let _ = fs::remove_file(trap);
+    fs::copy(artifact, trap).map_err(|e| BenchError::Run(e.to_string()))?;
+    sample.push(("binary_written".to_owned(), now_ms() - start));
+
+    let mut child = Command::new(trap)
+        .envs(emulator.env())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| BenchError::Run(e.to_string()))?;
+    sample.push(("process_spawned".to_owned(), now_ms() - start));
+
+    let phases = read_phases(child.stdout.take().unwrap());
+    let mut stderr = child.stderr.take().unwrap();
+    let errors = thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+
+    // waits in small steps to notice a binary that exits without answering
+    let deadline = Instant::now() + Duration::from_millis(args.timeout);
+    let (result, exited) = loop {
+        match outcome.recv_timeout(Duration::from_millis(10)) {
+            Err(RecvTimeoutError::Timeout) if Instant::now() < deadline => {}
+            result => break (result, None),
+        }
+
+        if let Ok(Some(status)) = child.try_wait() {
+            break (Err(RecvTimeoutError::Disconnected), Some(status));
+        }
+    };
+    let received = now_ms() - start;
+
+    // the response is all a cold start is about, the process doesn't need to shut down cleanly
+    let _ = child.kill();
+    let _ = child.wait();
+    drop(emulator);
+
+    let phases = phases.join().unwrap_or_default();
+    let errors = errors.join().unwrap_or_default();
+
+    let status = match result {
+        Ok(Outcome::Response(response)) => read_function_url_response(&response).status,
+        Ok(Outcome::Error(error)) => {
+            return Err(BenchError::Invocation(
+                String::from_utf8_lossy(&error).into_owned(),
+                errors,
+            ))
+        }
+        Err(RecvTimeoutError::Timeout) => return Err(BenchError::Timeout(args.timeout, errors)),
+        Err(RecvTimeoutError::Disconnected) => {
+            let text = match exited {
+                Some(status) => format!("the binary exited with {status}"),
+                None => "the emulator stopped".to_owned(),
+            };
+            return Err(BenchError::Exited(text, errors));
+        }
+    };
+
+    sample.extend(
+        phases
+            .into_iter()
+            .filter(|line| PHASES.contains(&line.phase.as_str()))
+            .map(|line| (line.phase, line.ms - start)),
+    );
+    sample.push(("response_received".to_owned(), received));
+
+    Ok((sample, status))
+}
+
+/// The nearest-rank percentile of sorted values.
+fn percentile(sorted: &[f64], percentile: f64) -> f64 {
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn print_report(samples: &[Sample]) {
+    println!();
+    println!(
+        "Cold start over {} runs, in milliseconds since the binary started being written",
+        samples.len()
+    );
+
+    let mut header = format!("{:<22}{:>10}", "phase", "min");
+    for p in PERCENTILES {
+        header.push_str(&format!("{:>10}", format!("p{p}")));
+    }
+    header.push_str(&format!("{:>10}", "max"));
+    println!("{header}");
+
+    for phase in PHASES {
+        let mut values = samples
+            .iter()
+            .filter_map(|sample| {
+                sample
+                    .iter()
+                    .find(|(name, _)| name == phase)
+                    .map(|(_, ms)| *ms)
+            })
+            .collect::<Vec<_>>();
+
+        if values.is_empty() {
+            println!("{phase:<22}{:>10}", "-");
+            continue;
+        }
+
+        values.sort_by(f64::total_cmp);
+
+        let mut row = format!("{phase:<22}{:>10.2}", values[0]);
+        for p in PERCENTILES {
+            row.push_str(&format!("{:>10.2}", percentile(&values, *p)));
+        }
+        row.push_str(&format!("{:>10.2}", values[values.len() - 1]));
+        println!("{row}");
+    }
+}
+
+fn run_cold_start(args: &ColdStartArgs) -> Result<(), BenchError> {
+    if args.runs == 0 {
+        return Err(BenchError::Run("--runs must be at least 1".to_owned()));
+    }
+
+    let metadata = get_cargo_metadata().map_err(|e| BenchError::Metadata(e.to_string()))?;
+    let bench_dir = metadata.target_directory.join("genezio/bench");
+
+    let artifact = match &args.artifact {
+        Some(artifact) => artifact.clone(),
+        None => {
+            println!("Building for the host");
+            cargo_build_host_lambda(&metadata, args.package.as_deref(), args.bin.as_deref())
+                .map_err(BenchError::Build)?
+                .executable
+        }
+    };
+
+    fs::create_dir_all(&bench_dir).map_err(|e| BenchError::Run(e.to_string()))?;
+    let trap = bench_dir.join("trap");
+
+    let mut samples = Vec::new();
+    for run in 1..=args.runs {
+        let (sample, status) = cold_start(args, &artifact, &trap)?;
+        let total = sample.last().map(|(_, ms)| *ms).unwrap_or_default();
+        println!("Run {run}/{}: {total:.2} ms, status {status}", args.runs);
+        samples.push(sample);
+    }
+
+    print_report(&samples);
+
+    Ok(())
+}
+
+pub fn run_bench(_global_opts: &GlobalOptions, args: &BenchArgs) -> Result<(), BenchError> {
+    match &args.command {
+        BenchCommand::ColdStart(args) => run_cold_start(args),
+    }
+}
+
+#[derive(Debug)]
+pub enum BenchError {
+    Metadata(String),
+    Build(BuildError),
+    Emulator(String),
+    Run(String),
+    /// The error the runtime posted and what the binary wrote to stderr.
+    Invocation(String, String),
+    /// The timeout and what the binary wrote to stderr.
+    Timeout(u64, String),
+    /// Why there is no response and what the binary wrote to stderr.
+    Exited(String, String),
+}
+
+/// Appends what the binary wrote to stderr, if anything.
+fn with_stderr(text: String, stderr: &str) -> String {
+    match stderr.trim_end() {
+        "" => text,
+        stderr => format!("{text}\n{stderr}"),
+    }
+}
+
+impl Display for BenchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "BenchError: {}",
+            match self {
+                BenchError::Metadata(text) => format!("failed to get cargo metadata: {text}"),
+                BenchError::Build(err) => err.to_string(),
+                BenchError::Emulator(text) => format!("failed to start the emulator: {text}"),
+                BenchError::Run(text) => format!("failed to run the cold start: {text}"),
+                BenchError::Invocation(error, stderr) => {
+                    with_stderr(format!("the invocation failed: {error}"), stderr)
+                }
+                BenchError::Timeout(timeout, stderr) => {
+                    with_stderr(format!("no response after {timeout} ms"), stderr)
+                }
+                BenchError::Exited(text, stderr) => with_stderr(text.clone(), stderr),
+            }
+        )
+    }
+}
+
+impl Error for BenchError {}
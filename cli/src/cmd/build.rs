@@ -1,11 +1,29 @@
 use crate::{
-    metadata::{get_cargo_metadata, CargoMetadata},
+    artifacts::{read_build_messages, BinArtifact, Diagnostic},
+    build_manifest::{build_id, BuildManifest, BuiltBinary},
+    cargo_config::{env_rustflags, host_triple, CargoConfig},
+    manifest::{GenezioManifest, ManifestError},
+    metadata::{get_cargo_metadata, CargoMetadata, GenezioBin},
     options::GlobalOptions,
+    sdk,
+    services::{read_metadata, Metadata, MethodMetadata, ServiceMetadata},
 };
+
+use super::doctor;
 use base64::{engine::general_purpose, Engine as _};
-use clap::Args;
-use is_executable::IsExecutable;
-use std::{error::Error, fmt::Display, fs, process::Command};
+use clap::{Args, ValueEnum};
+use std::{
+    env,
+    error::Error,
+    ffi::OsStr,
+    fmt::Display,
+    fs,
+    io::{self, BufReader, Write},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    time::SystemTime,
+};
 
 #[derive(Debug, Args)]
 pub struct BuildArgs {
@@ -13,145 +31,1186 @@ pub struct BuildArgs {
     #[clap(long, short = 'd')]
     pub debug: bool,
 
+    /// Build with this cargo profile instead of `release`
+    #[clap(long, conflicts_with = "debug")]
+    pub profile: Option<String>,
+
+    /// Features to activate, passed to cargo as they are
+    #[clap(long, short = 'F')]
+    pub features: Vec<String>,
+
+    /// Activate all the features
+    #[clap(long)]
+    pub all_features: bool,
+
+    /// Don't activate the `default` feature
+    #[clap(long)]
+    pub no_default_features: bool,
+
+    /// Optimize the binary for size instead of speed (`z` by default, or `s`), with lto, one
+    /// codegen unit, `panic = "abort"` and stripped symbols on top of the profile. The debug info
+    /// is kept in target/genezio/symbols for `genezio-rs symbolicate`
+    #[clap(long, value_enum, num_args = 0..=1, default_missing_value = "z")]
+    pub optimize_size: Option<SizeLevel>,
+
     /// Clean before building
     #[clap(long, short = 'c')]
     pub clean: bool,
+
+    /// Package to build, for workspaces with several packages
+    #[clap(long, short = 'p')]
+    pub package: Option<String>,
+
+    /// Binary to package, for packages with several binaries
+    #[clap(long)]
+    pub bin: Option<String>,
+
+    /// Where to write the TypeScript client (defaults to target/genezio/sdk/typescript)
+    #[clap(long)]
+    pub ts_sdk_dir: Option<PathBuf>,
+
+    /// Where to write the Rust client crate (defaults to target/genezio/sdk/rust)
+    #[clap(long)]
+    pub rust_sdk_dir: Option<PathBuf>,
+
+    /// How the generated JavaScript class runs the binary
+    #[clap(long, value_enum, default_value_t = Runtime::Bridge)]
+    pub runtime: Runtime,
+
+    /// Compress the binary embedded in index.js (only used with `--runtime trap`)
+    #[clap(long, value_enum, default_value_t = Compression::None)]
+    pub compress: Compression,
+
+    /// What to build the binary for
+    #[clap(long, value_enum, default_value_t = Target::Aarch64Musl)]
+    pub target: Target,
+
+    /// How to link the binary for the target (ignored for `--target host`)
+    #[clap(long, value_enum, default_value_t = Linker::Auto)]
+    pub linker: Linker,
 }
 
-fn copy_genezio_manifest(metadata: &CargoMetadata) -> Result<(), BuildError> {
+fn prepare_out_dir(metadata: &CargoMetadata) -> Result<(), BuildError> {
     let target_dir = metadata.get_genezio_out_dir();
-    if !target_dir.exists() {
-        fs::create_dir_all(&target_dir).map_err(|_| BuildError::GenezioManifestNotFound)?;
+
+    // start clean so class files from earlier builds don't get deployed
+    if target_dir.exists() {
+        fs::remove_dir_all(&target_dir)
+            .map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
     }
 
-    let genezio_manifest = metadata.workspace_root.join("genezio.yaml");
+    fs::create_dir_all(&target_dir).map_err(|e| BuildError::RenderBuildOutput(e.to_string()))
+}
 
-    if !genezio_manifest.exists() {
-        return Err(BuildError::GenezioManifestNotFound);
+/// Starts a build of the Lambda variant of the app for `target`, linked with `linker`. The flags
+/// it needs are added to the ones from the user's environment or cargo config.
+fn cargo_build_command(
+    metadata: &CargoMetadata,
+    target: Target,
+    linker: Linker,
+) -> Result<Command, BuildError> {
+    let triple = match target.triple() {
+        Some(triple) => triple.to_owned(),
+        None => host_triple().map_err(BuildError::CargoConfig)?,
+    };
+    let config = env::current_dir()
+        .map_err(|e| e.to_string())
+        .and_then(|dir| CargoConfig::read(&dir))
+        .map_err(BuildError::CargoConfig)?;
+
+    let mut rustflags = target.rustflags().to_vec();
+    if linker == Linker::Gcc && target != Target::Host {
+        // gcc's linker needs libgcc for the builtins musl doesn't have, zig and the cross images
+        // bring their own
+        rustflags.extend(["-C", "link-arg=-lgcc"]);
+    }
+    if target != Target::Host {
+        // names the debug info --optimize-size splits out, see `genezio-rs symbolicate`
+        rustflags.extend(["-C", "link-arg=-Wl,--build-id"]);
     }
+    rustflags.extend(["--cfg", "genezio_with_lambda"]);
 
-    fs::copy(genezio_manifest, target_dir.join("genezio.yaml"))
-        .map_err(|_| BuildError::GenezioManifestNotFound)?;
+    let (program, subcommand) = linker.command();
+    let mut command = Command::new(program);
+    command
+        .arg(subcommand)
+        .arg("--message-format=json-diagnostic-rendered-ansi");
 
-    Ok(())
+    if target == Target::Host {
+        // it gets its own target directory, switching between this and a regular build
+        // would otherwise rebuild everything each time
+        command
+            .arg("--target-dir")
+            .arg(metadata.get_genezio_host_dir());
+    } else {
+        command.arg("--target").arg(&triple);
+    }
+
+    match env_rustflags() {
+        // cargo ignores the config files when these are set
+        Some(mut flags) => {
+            flags.extend(rustflags.iter().map(|flag| flag.to_string()));
+            command.env("CARGO_ENCODED_RUSTFLAGS", flags.join("\x1f"));
+        }
+        // cargo joins ours with the user's target.<triple>.rustflags, but skips
+        // build.rustflags once any of them is set
+        None => {
+            let mut flags = if config.has_target_rustflags(&triple) {
+                vec![]
+            } else {
+                config.build_rustflags()
+            };
+            flags.extend(rustflags.iter().map(|flag| flag.to_string()));
+
+            let flags = flags.into_iter().map(toml::Value::String).collect();
+            command.arg("--config").arg(format!(
+                "target.{triple}.rustflags={}",
+                toml::Value::Array(flags)
+            ));
+        }
+    }
+
+    // a linker set by the user wins over ours
+    if let (Linker::Gcc, Some(gcc), None) = (linker, target.gcc(), config.linker(&triple)) {
+        command.arg("--config").arg(format!(
+            "target.{triple}.linker={}",
+            toml::Value::String(gcc.to_owned())
+        ));
+    }
+
+    Ok(command)
 }
 
-fn cargo_build(clean: bool, release: bool) -> Result<(), BuildError> {
-    if clean {
-        Command::new("cargo")
+/// Runs a build from `cargo_build_command`, printing its diagnostics, and returns the binaries it
+/// produced.
+fn run_cargo_build(mut command: Command) -> Result<Vec<BinArtifact>, BuildError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound if program != "cargo" => BuildError::LinkerNotFound(program),
+            _ => BuildError::CargoBuild,
+        })?;
+
+    let messages = child
+        .stdout
+        .take()
+        .map(|stdout| read_build_messages(BufReader::new(stdout)))
+        .unwrap_or_default();
+    let status = child.wait().map_err(|_| BuildError::CargoBuild)?;
+
+    if status.success() {
+        return Ok(messages.artifacts);
+    }
+
+    if let Some(linker) = messages.errors.iter().find_map(Diagnostic::missing_linker) {
+        return Err(BuildError::LinkerNotFound(linker.to_owned()));
+    }
+
+    if messages.errors.is_empty() {
+        return Err(BuildError::CargoFailed(status));
+    }
+
+    Err(BuildError::Compile(messages.errors))
+}
+
+impl BuildArgs {
+    /// The cargo profile the build uses.
+    fn profile_name(&self) -> String {
+        match (&self.profile, self.debug) {
+            (Some(profile), _) => profile.clone(),
+            (None, true) => "dev".to_owned(),
+            (None, false) => "release".to_owned(),
+        }
+    }
+}
+
+fn cargo_build(
+    metadata: &CargoMetadata,
+    args: &BuildArgs,
+    linker: Linker,
+    split_debuginfo: bool,
+    bins: &[GenezioBin],
+) -> Result<Vec<BinArtifact>, BuildError> {
+    if args.clean {
+        let status = Command::new("cargo")
             .arg("clean")
             .status()
             .map_err(|_| BuildError::CargoBuild)?;
+
+        if !status.success() {
+            return Err(BuildError::CargoFailed(status));
+        }
     }
 
-    if release {
-        Command::new("cargo")
-            .arg("build")
-            .arg("--target")
-            .arg("aarch64-unknown-linux-musl")
-            .arg("--config")
-            .arg("target.aarch64-unknown-linux-musl.linker='aarch64-linux-gnu-gcc'")
-            .arg("--config")
-            .arg("target.aarch64-unknown-linux-musl.rustflags=[ \"-C\", \"target-feature=+crt-static\", \"-C\", \"link-arg=-lgcc\", \"--cfg\", \"genezio_with_lambda\" ]")
-            .arg("--release")
-            .status()
-    } else {
-        Command::new("cargo")
-            .arg("build")
-            .arg("--target")
-            .arg("aarch64-unknown-linux-musl")
-            .arg("--config")
-            .arg("target.aarch64-unknown-linux-musl.linker='aarch64-linux-gnu-gcc'")
-            .arg("--config")
-            .arg("target.aarch64-unknown-linux-musl.rustflags=[ \"-C\", \"target-feature=+crt-static\", \"-C\", \"link-arg=-lgcc\", \"--cfg\", \"genezio_with_lambda\" ]")
-            .status()
+    let mut command = cargo_build_command(metadata, args.target, linker)?;
+
+    if let Some(profile) = &args.profile {
+        command.arg("--profile").arg(profile);
+    } else if !args.debug {
+        command.arg("--release");
+    }
+
+    for features in &args.features {
+        command.arg("--features").arg(features);
+    }
+
+    if args.all_features {
+        command.arg("--all-features");
+    }
+
+    if args.no_default_features {
+        command.arg("--no-default-features");
+    }
+
+    if let Some(level) = args.optimize_size {
+        let profile = args.profile_name();
+        let mut settings = vec![
+            ("lto", "true"),
+            ("codegen-units", "1"),
+            ("panic", "\"abort\""),
+            ("opt-level", level.as_toml()),
+        ];
+
+        if split_debuginfo {
+            // line tables are enough to symbolicate backtraces, `strip_artifacts` moves them out
+            settings.extend([("debug", "\"line-tables-only\""), ("strip", "\"none\"")]);
+        } else {
+            settings.push(("strip", "\"symbols\""));
+        }
+
+        for (key, value) in settings {
+            command
+                .arg("--config")
+                .arg(format!("profile.{profile}.{key}={value}"));
+        }
+    }
+
+    if let Some(package) = &args.package {
+        command.arg("--package").arg(package);
+    }
+
+    if let Some(bin) = &args.bin {
+        command.arg("--bin").arg(bin);
+    }
+
+    let mut packages = bins.iter().map(|bin| &bin.package).collect::<Vec<_>>();
+    packages.sort();
+    packages.dedup();
+    for package in packages {
+        command.arg("--package").arg(package);
+    }
+
+    for bin in bins {
+        command.arg("--bin").arg(&bin.name);
+    }
+
+    run_cargo_build(command)
+}
+
+/// The tools that split the debug info out of the binaries for a target and strip them.
+struct Binutils {
+    objcopy: String,
+    strip: String,
+}
+
+/// Finds the `tool` from binutils that handles binaries for `target`.
+fn find_binutil(target: Target, tool: &str) -> Option<String> {
+    let candidates = match target.gcc() {
+        Some(gcc) => vec![
+            gcc.replace("-gcc", &format!("-{tool}")),
+            format!("llvm-{tool}"),
+        ],
+        None => vec![tool.to_owned()],
+    };
+
+    candidates
+        .into_iter()
+        .find(|program| doctor::command_works(program, &["--version"]))
+}
+
+fn find_binutils(target: Target) -> Option<Binutils> {
+    Some(Binutils {
+        objcopy: find_binutil(target, "objcopy")?,
+        strip: find_binutil(target, "strip")?,
+    })
+}
+
+fn run_binutil(program: &str, args: &[&OsStr]) -> Result<(), BuildError> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| BuildError::Strip(format!("{program}: {e}")))?;
+
+    if !status.success() {
+        return Err(BuildError::Strip(format!("{program} exited with {status}")));
     }
-    .map_err(|_| BuildError::CargoBuild)?;
 
     Ok(())
 }
 
-fn get_js_str_from_exe(bytes: &[u8]) -> Result<String, BuildError> {
-    let base64 = general_purpose::STANDARD.encode(bytes);
+/// Moves the debug info of the binaries to `<symbols_dir>/<build id>/<bin>.debug` and strips
+/// them. Returns the size of every binary before and after.
+fn strip_artifacts(
+    binutils: Option<&Binutils>,
+    artifacts: &[BinArtifact],
+    symbols_dir: &Path,
+) -> Result<Vec<(String, u64, u64)>, BuildError> {
+    let size = |path: &PathBuf| {
+        fs::metadata(path)
+            .map(|metadata| metadata.len())
+            .map_err(|e| BuildError::Strip(e.to_string()))
+    };
+
+    let mut sizes = Vec::new();
+    for artifact in artifacts {
+        let before = size(&artifact.executable)?;
+
+        if let Some(binutils) = binutils {
+            let bytes =
+                fs::read(&artifact.executable).map_err(|e| BuildError::Strip(e.to_string()))?;
+
+            match build_id(&bytes) {
+                Some(id) => {
+                    let dir = symbols_dir.join(&id);
+                    let debug = dir.join(format!("{}.debug", artifact.name));
+
+                    // a binary cargo found up to date was stripped by an earlier build, which
+                    // kept its debug info under the same build id
+                    if !debug.exists() {
+                        fs::create_dir_all(&dir).map_err(|e| BuildError::Strip(e.to_string()))?;
+                        run_binutil(
+                            &binutils.objcopy,
+                            &[
+                                "--only-keep-debug".as_ref(),
+                                artifact.executable.as_os_str(),
+                                debug.as_os_str(),
+                            ],
+                        )?;
+                    }
+
+                    println!("Debug info of {} is in {}", artifact.name, debug.display());
+                }
+                None => println!(
+                    "{} has no build id, not keeping its debug info",
+                    artifact.name
+                ),
+            }
+
+            run_binutil(
+                &binutils.strip,
+                &["--strip-all".as_ref(), artifact.executable.as_os_str()],
+            )?;
+        }
+
+        sizes.push((artifact.name.clone(), before, size(&artifact.executable)?));
+    }
+
+    Ok(sizes)
+}
+
+fn print_sizes(sizes: &[(String, u64, u64)], out_dir: &Path, files: &[String]) {
+    println!("{:<24}{:>12}{:>12}{:>8}", "", "built", "stripped", "saved");
+    for (name, before, after) in sizes {
+        let saved = 100.0 - *after as f64 * 100.0 / (*before).max(1) as f64;
+        println!(
+            "{:<24}{:>12}{:>12}{:>7.1}%",
+            name,
+            format_size(*before as usize),
+            format_size(*after as usize),
+            saved
+        );
+    }
+
+    for file in files {
+        if let Ok(metadata) = fs::metadata(out_dir.join(file)) {
+            println!(
+                "{:<24}{:>12}{:>12}",
+                file,
+                "",
+                format_size(metadata.len() as usize)
+            );
+        }
+    }
+}
+
+/// Builds a release binary for the host with the same cfg as the Lambda build, so it goes through
+/// `lambda_http` like it does on genezio.
+pub fn cargo_build_host_lambda(
+    metadata: &CargoMetadata,
+    package: Option<&str>,
+    bin: Option<&str>,
+) -> Result<BinArtifact, BuildError> {
+    let mut command = cargo_build_command(metadata, Target::Host, Linker::Gcc)?;
+    command.arg("--release");
+
+    if let Some(package) = package {
+        command.arg("--package").arg(package);
+    }
+
+    if let Some(bin) = bin {
+        command.arg("--bin").arg(bin);
+    }
+
+    select_artifact(run_cargo_build(command)?, bin)
+}
+
+pub fn select_artifact(
+    artifacts: Vec<BinArtifact>,
+    bin: Option<&str>,
+) -> Result<BinArtifact, BuildError> {
+    let mut artifacts = artifacts
+        .into_iter()
+        .filter(|artifact| bin.is_none_or(|bin| artifact.name == bin))
+        .collect::<Vec<_>>();
+
+    match artifacts.len() {
+        0 => Err(BuildError::NoBinary),
+        1 => Ok(artifacts.remove(0)),
+        _ => Err(BuildError::AmbiguousBinary(
+            artifacts
+                .into_iter()
+                .map(|artifact| artifact.name)
+                .collect(),
+        )),
+    }
+}
+
+/// Prints a cold start phase the same way `genezio::cold_start` does.
+const JS_COLD_START: &str = "
+function coldStart(phase) {
+  const ms = performance.timeOrigin + performance.now();
+  console.log(`genezio:cold-start ${JSON.stringify({ phase, ms })}`);
+}
+";
+
+const JS_BRIDGE: &str = "
+import { spawn } from 'child_process';
+import { accessSync, chmodSync, constants, copyFileSync, unlinkSync } from 'fs';
+import { createServer } from 'net';
+import { dirname, join } from 'path';
+import { fileURLToPath } from 'url';
+
+const BRIDGE_BIN = join(dirname(fileURLToPath(import.meta.url)), '{bin}');
+
+function bridgeExecutable() {
+  try {
+    accessSync(BRIDGE_BIN, constants.X_OK);
+    return BRIDGE_BIN;
+  } catch {
+    // deploy archives don't always keep the executable bit
+    copyFileSync(BRIDGE_BIN, '/tmp/{bin}');
+    chmodSync('/tmp/{bin}', '755');
+    return '/tmp/{bin}';
+  }
+}
+
+// same as the `RpcError` of the generated TypeScript client.
+class RpcError extends Error {
+  constructor(code, message, data) {
+    super(message);
+    this.name = 'RpcError';
+    this.code = code;
+    this.data = data;
+  }
+}
+
+class Bridge {
+  constructor() {
+    this.nextId = 1;
+    this.pending = new Map();
+    this.ready = null;
+    this.child = null;
+  }
+
+  start() {
+    if (this.ready) {
+      return this.ready;
+    }
+
+    this.ready = new Promise((resolve, reject) => {
+      const socketPath = `/tmp/genezio-bridge-{bin}-${process.pid}.sock`;
+      try {
+        unlinkSync(socketPath);
+      } catch {}
+
+      const server = createServer((socket) => {
+        server.close();
+        this.attach(socket);
+        resolve(socket);
+      });
+
+      server.on('error', reject);
+      server.listen(socketPath, () => {
+        const executable = bridgeExecutable();
+        coldStart('binary_written');
+
+        const child = spawn(executable, [], {
+          stdio: 'inherit',
+          env: { ...process.env, GENEZIO_BRIDGE: socketPath },
+        });
+        coldStart('process_spawned');
+        this.child = child;
+
+        const fail = (err) => {
+          this.child = null;
+          server.close();
+          this.reset(err);
+          reject(err);
+        };
+
+        child.on('error', fail);
+        child.on('exit', (code) => fail(new Error(`bridge exited with code ${code}`)));
+      });
+    });
+
+    return this.ready;
+  }
+
+  reset(err) {
+    this.ready = null;
+    for (const pending of this.pending.values()) {
+      pending.reject(err);
+    }
+    this.pending.clear();
+  }
+
+  attach(socket) {
+    let buffer = Buffer.alloc(0);
+
+    socket.on('data', (chunk) => {
+      buffer = Buffer.concat([buffer, chunk]);
+
+      while (buffer.length >= 4) {
+        const length = buffer.readUInt32BE(0);
+        if (buffer.length < 4 + length) {
+          break;
+        }
+
+        const frame = JSON.parse(buffer.subarray(4, 4 + length).toString());
+        buffer = buffer.subarray(4 + length);
+
+        const pending = this.pending.get(frame.id);
+        if (pending) {
+          this.pending.delete(frame.id);
+          pending.resolve({
+            status: frame.status,
+            headers: frame.headers,
+            body: Buffer.from(frame.body, 'base64'),
+          });
+        }
+      }
+    });
+  }
+
+  async request(method, path, headers, body) {
+    const socket = await this.start();
+    const id = this.nextId++;
+
+    const payload = Buffer.from(
+      JSON.stringify({ id, method, path, headers, body: body.toString('base64') })
+    );
+    const header = Buffer.alloc(4);
+    header.writeUInt32BE(payload.length);
+
+    return await new Promise((resolve, reject) => {
+      this.pending.set(id, { resolve, reject });
+      socket.write(Buffer.concat([header, payload]));
+    });
+  }
+
+  async rpc(method, params) {
+    const body = Buffer.from(JSON.stringify({ jsonrpc: '2.0', method, params, id: 1 }));
+    const response = await this.request('POST', '/', { 'content-type': 'application/json' }, body);
+
+    // the router answers every call with 200, anything else didn't come from the rpc handler
+    if (response.status !== 200) {
+      throw new Error(`${method} failed with status ${response.status}: ${response.body.toString()}`);
+    }
+
+    const result = JSON.parse(response.body.toString());
+    if (result.error) {
+      throw new RpcError(result.error.code, result.error.message, result.error.data);
+    }
+
+    return result.result;
+  }
+}
+
+const bridge = new Bridge();
+
+// give the binary a chance to run its shutdown hook before the runtime goes away
+process.on('SIGTERM', () => {
+  if (!bridge.child) {
+    process.exit(0);
+  }
+
+  bridge.child.once('exit', () => process.exit(0));
+  bridge.child.kill('SIGTERM');
+});
+";
+
+const JS_BRIDGE_HTTP_CLASS: &str = "
+@GenezioDeploy()
+export class {class} {
+  constructor() {
+    bridge.start().catch(() => {});
+  }
+
+  @GenezioHttpMethod()
+  async call(request) {
+    const path = request.http.path.replace(/^\\/{class}\\/call/, '') || '/';
+    const query = new URLSearchParams(request.queryStringParameters || {}).toString();
+    const body =
+      request.body === undefined
+        ? Buffer.alloc(0)
+        : Buffer.from(typeof request.body === 'string' ? request.body : JSON.stringify(request.body));
+
+    const response = await bridge.request(
+      request.http.method,
+      query ? `${path}?${query}` : path,
+      request.headers || {},
+      body
+    );
+
+    return {
+      statusCode: String(response.status),
+      headers: response.headers,
+      body: response.body.toString(),
+    };
+  }
+}
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Runtime {
+    /// Ship the binary next to index.js and forward every call to it
+    Bridge,
+    /// Embed the binary in index.js and let it take over the lambda
+    Trap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Target {
+    /// Lambda on arm64, what genezio runs
+    #[value(name = "aarch64-unknown-linux-musl")]
+    Aarch64Musl,
+    /// Lambda on x86_64
+    #[value(name = "x86_64-unknown-linux-musl")]
+    X86_64Musl,
+    /// This machine, to debug the Lambda variant of the binary natively
+    Host,
+}
+
+impl Target {
+    /// The target triple passed to cargo, none for the host.
+    pub fn triple(self) -> Option<&'static str> {
+        match self {
+            Target::Aarch64Musl => Some("aarch64-unknown-linux-musl"),
+            Target::X86_64Musl => Some("x86_64-unknown-linux-musl"),
+            Target::Host => None,
+        }
+    }
+
+    /// The GCC that links for the target, none to let cargo pick.
+    pub fn gcc(self) -> Option<&'static str> {
+        match self {
+            Target::Aarch64Musl => Some("aarch64-linux-gnu-gcc"),
+            Target::X86_64Musl => Some("x86_64-linux-gnu-gcc"),
+            Target::Host => None,
+        }
+    }
+
+    /// Flags for rustc, besides the ones the linker needs and the `genezio_with_lambda` cfg
+    /// every target gets.
+    fn rustflags(self) -> &'static [&'static str] {
+        match self {
+            // a static binary doesn't depend on the libc of the Lambda image
+            Target::Aarch64Musl | Target::X86_64Musl => &["-C", "target-feature=+crt-static"],
+            Target::Host => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Linker {
+    /// The first of the others `genezio-rs doctor` finds, in this order
+    Auto,
+    /// The GCC cross toolchain, like `aarch64-linux-gnu-gcc`
+    Gcc,
+    /// `cargo zigbuild`, linking with `zig cc`
+    Zig,
+    /// `cross`, building in a container that has the toolchain
+    Cross,
+}
+
+impl Linker {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Linker::Auto => "auto",
+            Linker::Gcc => "gcc",
+            Linker::Zig => "zig",
+            Linker::Cross => "cross",
+        }
+    }
+
+    /// The program and subcommand that build with this linker.
+    fn command(self) -> (&'static str, &'static str) {
+        match self {
+            Linker::Auto | Linker::Gcc => ("cargo", "build"),
+            Linker::Zig => ("cargo", "zigbuild"),
+            Linker::Cross => ("cross", "build"),
+        }
+    }
+
+    /// The linker to build for `target` with, host builds always go through plain cargo.
+    pub fn resolve(self, target: Target) -> Result<Linker, BuildError> {
+        match (self, target.triple()) {
+            (_, None) => Ok(Linker::Gcc),
+            (Linker::Auto, Some(triple)) => {
+                doctor::find_linker(target).ok_or(BuildError::NoLinker(triple.to_owned()))
+            }
+            (linker, Some(_)) => Ok(linker),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SizeLevel {
+    /// `opt-level = "z"`, the smallest
+    Z,
+    /// `opt-level = "s"`, small but keeps loop vectorization
+    S,
+}
+
+impl SizeLevel {
+    fn as_toml(self) -> &'static str {
+        match self {
+            SizeLevel::Z => "\"z\"",
+            SizeLevel::S => "\"s\"",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    /// Embed the binary as is
+    None,
+    /// Compress with gzip
+    Gzip,
+    /// Compress with brotli, smaller than gzip but slower to build
+    Brotli,
+}
+
+impl Compression {
+    fn compress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(bytes.to_vec()),
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            Compression::Brotli => {
+                let mut out = Vec::new();
+                {
+                    let mut encoder = brotli::CompressorWriter::new(&mut out, 4096, 9, 22);
+                    encoder.write_all(bytes)?;
+                }
+                Ok(out)
+            }
+        }
+    }
+
+    /// The Node expression turning the `TRAP_BIN` buffer back into the binary.
+    fn js_decompress(self) -> &'static str {
+        match self {
+            Compression::None => "TRAP_BIN",
+            Compression::Gzip => "gunzipSync(TRAP_BIN)",
+            Compression::Brotli => "brotliDecompressSync(TRAP_BIN)",
+        }
+    }
+}
+
+fn format_size(bytes: usize) -> String {
+    format!("{:.2} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn get_js_class(service: &ServiceMetadata, runtime: Runtime) -> String {
+    let methods = service
+        .methods
+        .iter()
+        .map(|method| {
+            let params = method
+                .params
+                .iter()
+                .map(|param| param.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let body = match runtime {
+                Runtime::Bridge => format!(
+                    "
+    return await bridge.rpc('{service}.{name}', [{params}]);
+  ",
+                    service = service.name,
+                    name = method.name
+                ),
+                Runtime::Trap => " ".to_owned(),
+            };
+
+            format!(
+                "
+
+  @GenezioMethod()
+  async {name}({params}) {{{body}}}",
+                name = method.name
+            )
+        })
+        .collect::<String>();
+
+    let constructor = match runtime {
+        Runtime::Bridge => "bridge.start().catch(() => {});",
+        Runtime::Trap => "startTrap();",
+    };
+
+    format!(
+        "
+@GenezioDeploy()
+export class {name} {{
+  constructor() {{
+    {constructor}
+  }}{methods}
+}}
+",
+        name = service.name
+    )
+}
+
+fn get_js_str_from_exe(
+    bytes: &[u8],
+    services: &[ServiceMetadata],
+    http_class: &str,
+    compression: Compression,
+) -> Result<String, BuildError> {
+    let compressed = compression
+        .compress(bytes)
+        .map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
+
+    if compression == Compression::None {
+        println!("Embedding binary ({})", format_size(bytes.len()));
+    } else {
+        println!(
+            "Embedding binary ({} -> {} with {:?})",
+            format_size(bytes.len()),
+            format_size(compressed.len()),
+            compression
+        );
+    }
+
+    let base64 = general_purpose::STANDARD.encode(compressed);
+    let decompress = compression.js_decompress();
+
+    let classes = if services.is_empty() {
+        // plain http apps still need a class for genezio to deploy
+        get_js_class(
+            &ServiceMetadata {
+                name: http_class.to_owned(),
+                methods: vec![MethodMetadata {
+                    name: "call".to_owned(),
+                    params: vec![],
+                    returns: None,
+                }],
+            },
+            Runtime::Trap,
+        )
+    } else {
+        services
+            .iter()
+            .map(|service| get_js_class(service, Runtime::Trap))
+            .collect::<String>()
+    };
 
     Ok(format!(
         "
 import {{ writeFileSync, chmodSync }} from 'fs';
 import {{ createRequire }} from 'module';
 import {{ execSync }} from 'child_process';
+import {{ gunzipSync, brotliDecompressSync }} from 'zlib';
 
 const TRAP_BIN = Buffer.from('{base64}', 'base64');
+{cold_start}
+function startTrap() {{
+  writeFileSync('/tmp/trap', {decompress});
+  chmodSync('/tmp/trap', '755');
+  coldStart('binary_written');
 
-@GenezioDeploy()
-export class Service {{
-  constructor() {{
-    writeFileSync('/tmp/trap', TRAP_BIN);
-    chmodSync('/tmp/trap', '755');
-
-    console.log('trap start time', Date.now());
-    execSync('/tmp/trap', {{ stdio: 'inherit' }});
-  }}
-
-  @GenezioMethod()
-  async call() {{ }}
+  // execSync only returns once the trap exits, so this is logged right before it starts
+  coldStart('process_spawned');
+  execSync('/tmp/trap', {{ stdio: 'inherit' }});
 }}
-"
+{classes}",
+        cold_start = JS_COLD_START
     )
     .trim()
     .to_owned())
 }
 
-fn render_build_output(metadata: &CargoMetadata, release: bool) -> Result<(), BuildError> {
+fn get_js_str_for_bridge(bin_name: &str, services: &[ServiceMetadata], http_class: &str) -> String {
+    let classes = if services.is_empty() {
+        JS_BRIDGE_HTTP_CLASS.replace("{class}", http_class)
+    } else {
+        services
+            .iter()
+            .map(|service| get_js_class(service, Runtime::Bridge))
+            .collect::<String>()
+    };
+
+    format!(
+        "{}{JS_COLD_START}{classes}",
+        JS_BRIDGE.replace("{bin}", bin_name)
+    )
+    .trim()
+    .to_owned()
+}
+
+fn write_sdks(
+    metadata: &CargoMetadata,
+    args: &BuildArgs,
+    bin_name: &str,
+    services: &Metadata,
+) -> Result<(), BuildError> {
+    let sdk_dir = args
+        .ts_sdk_dir
+        .clone()
+        .unwrap_or_else(|| metadata.get_genezio_sdk_dir().join("typescript"));
+
+    sdk::typescript::write_sdk(&sdk_dir, services).map_err(|e| BuildError::Sdk(e.to_string()))?;
+    println!("TypeScript client written to {}", sdk_dir.display());
+
+    let (sdk_dir, standalone) = match &args.rust_sdk_dir {
+        Some(dir) => (dir.clone(), false),
+        None => (metadata.get_genezio_sdk_dir().join("rust"), true),
+    };
+
+    sdk::rust::write_sdk(
+        &sdk_dir,
+        &format!("{bin_name}-client"),
+        services,
+        standalone,
+    )
+    .map_err(|e| BuildError::Sdk(e.to_string()))?;
+    println!("Rust client written to {}", sdk_dir.display());
+
+    Ok(())
+}
+
+/// The class generated for a binary without services, which forwards plain http requests.
+fn http_class_name(bin_name: &str) -> String {
+    bin_name
+        .split(|ch: char| !ch.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word[..1].to_uppercase() + &word[1..])
+        .collect()
+}
+
+/// Writes the class file (and the binary itself, for the bridge) for one binary and returns
+/// the services found in it.
+fn render_bin(
+    metadata: &CargoMetadata,
+    args: &BuildArgs,
+    build: &BuildManifest,
+    artifact: &BinArtifact,
+    file_name: &str,
+    http_class: &str,
+) -> Result<Metadata, BuildError> {
     let out_dir = metadata.get_genezio_out_dir();
 
     if !out_dir.exists() {
         fs::create_dir_all(&out_dir).map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
     }
 
-    let release_dir = metadata.target_directory.join(if release {
-        "aarch64-unknown-linux-musl/release/"
-    } else {
-        "aarch64-unknown-linux-musl/debug/"
-    });
+    println!("Packaging {}", artifact.executable.display());
 
-    if !release_dir.exists() {
-        return Err(BuildError::RenderBuildOutput(
-            "release directory not found".to_string(),
-        ));
+    let in_bytes =
+        fs::read(&artifact.executable).map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
+    let bin_name = &artifact.name;
+
+    // refuse to package anything but what the build that just ran produced
+    build
+        .binary(bin_name)
+        .ok_or_else(|| BuildError::StaleBinary(format!("{bin_name} was not built")))?
+        .check(&in_bytes)
+        .map_err(BuildError::StaleBinary)?;
+
+    let services =
+        read_metadata(&in_bytes).map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
+
+    for service in &services.services {
+        println!("Found service {}", service.name);
     }
 
-    let Ok(release_read_dir) = fs::read_dir(release_dir) else {
-        return Err(BuildError::RenderBuildOutput(
-            "failed to read release directory".to_string(),
-        ));
+    let out_str = match args.runtime {
+        Runtime::Bridge => {
+            if args.compress != Compression::None {
+                println!("--compress only applies to --runtime trap, ignoring it");
+            }
+
+            let bin_path = out_dir.join(bin_name);
+            fs::write(&bin_path, &in_bytes)
+                .map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
+            fs::set_permissions(&bin_path, fs::Permissions::from_mode(0o755))
+                .map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
+
+            get_js_str_for_bridge(bin_name, &services.services, http_class)
+        }
+        Runtime::Trap => {
+            get_js_str_from_exe(&in_bytes, &services.services, http_class, args.compress)?
+        }
     };
 
-    for entry in release_read_dir {
-        let entry = entry.map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
-        let path = entry.path();
-        if path.is_file() && path.is_executable() {
-            let in_bytes =
-                fs::read(path.clone()).map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
+    let out_path = out_dir.join(file_name);
+    fs::write(out_path, out_str).map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
 
-            let out_path = out_dir.join("index.js");
-            let out_str = get_js_str_from_exe(&in_bytes)?;
-            fs::write(out_path, out_str)
-                .map_err(|e| BuildError::RenderBuildOutput(e.to_string()))?;
+    Ok(services)
+}
+
+fn render_build_output(
+    metadata: &CargoMetadata,
+    args: &BuildArgs,
+    build: &BuildManifest,
+    artifact: &BinArtifact,
+) -> Result<Vec<String>, BuildError> {
+    let services = render_bin(metadata, args, build, artifact, "index.js", "Service")?;
+
+    if !services.services.is_empty() {
+        write_sdks(metadata, args, &artifact.name, &services)?;
+    }
+
+    Ok(vec!["index.js".to_owned()])
+}
 
-            break;
+fn render_services_output(
+    metadata: &CargoMetadata,
+    args: &BuildArgs,
+    build: &BuildManifest,
+    manifest: &GenezioManifest,
+    bins: &[GenezioBin],
+    artifacts: &[BinArtifact],
+) -> Result<Vec<String>, BuildError> {
+    let mut services = Metadata::default();
+    let mut classes = Vec::<String>::new();
+    let mut files = Vec::new();
+
+    for bin in bins {
+        let artifact = artifacts
+            .iter()
+            .find(|artifact| artifact.name == bin.name)
+            .ok_or_else(|| BuildError::MissingBinary(bin.name.clone()))?;
+
+        let file_name = format!("{}.js", bin.name);
+        let http_class = http_class_name(&bin.name);
+        let bin_services = render_bin(metadata, args, build, artifact, &file_name, &http_class)?;
+
+        let bin_classes = if bin_services.services.is_empty() {
+            vec![http_class]
+        } else {
+            bin_services
+                .services
+                .iter()
+                .map(|service| service.name.clone())
+                .collect()
+        };
+
+        for class in bin_classes {
+            if classes.contains(&class) {
+                return Err(BuildError::DuplicateClass(class));
+            }
+            classes.push(class);
         }
+
+        files.push(file_name);
+        services.extend(bin_services);
     }
 
-    Ok(())
+    if !services.services.is_empty() {
+        write_sdks(metadata, args, &manifest.name, &services)?;
+    }
+
+    Ok(files)
 }
 
 pub fn run_build(_global_opts: &GlobalOptions, args: &BuildArgs) -> Result<(), BuildError> {
     println!("Starting build");
 
     let metadata = get_cargo_metadata().map_err(|e| BuildError::Metadata(e.to_string()))?;
-    copy_genezio_manifest(&metadata)?;
-    cargo_build(args.clean, !args.debug)?;
-    render_build_output(&metadata, !args.debug)?;
+    let mut manifest = GenezioManifest::read(&metadata.workspace_root.join("genezio.yaml"))
+        .map_err(BuildError::Manifest)?;
+    prepare_out_dir(&metadata)?;
+
+    // --bin and --package always pick a single binary
+    let bins = if args.bin.is_none() && args.package.is_none() {
+        metadata.get_genezio_bins().map_err(BuildError::Metadata)?
+    } else {
+        vec![]
+    };
+
+    let linker = args.linker.resolve(args.target)?;
+    if args.target != Target::Host {
+        println!("Linking with {}", linker.as_str());
+    }
+
+    let binutils = args.optimize_size.and_then(|_| find_binutils(args.target));
+    if args.optimize_size.is_some() && binutils.is_none() {
+        println!("Found no objcopy and strip for the target, cargo will strip the binaries without keeping their debug info");
+    }
+
+    let started = SystemTime::now();
+    let artifacts = cargo_build(&metadata, args, linker, binutils.is_some(), &bins)?;
+    let sizes = match args.optimize_size {
+        Some(_) => strip_artifacts(
+            binutils.as_ref(),
+            &artifacts,
+            &metadata.get_genezio_symbols_dir(),
+        )?,
+        None => vec![],
+    };
+
+    let target = match args.target.triple() {
+        Some(triple) => triple.to_owned(),
+        None => host_triple().map_err(BuildError::CargoConfig)?,
+    };
+    let mut build = BuildManifest::new(&metadata.workspace_root, target, args.profile_name());
+    for artifact in &artifacts {
+        build
+            .binaries
+            .push(BuiltBinary::read(artifact, started).map_err(BuildError::StaleBinary)?);
+    }
+
+    let files = if bins.is_empty() {
+        let artifact = select_artifact(artifacts, args.bin.as_deref())?;
+        render_build_output(&metadata, args, &build, &artifact)?
+    } else {
+        render_services_output(&metadata, args, &build, &manifest, &bins, &artifacts)?
+    };
+
+    let out_dir = metadata.get_genezio_out_dir();
+    manifest
+        .merge_classes(&files)
+        .map_err(BuildError::Manifest)?;
+    manifest
+        .write(&out_dir.join("genezio.yaml"))
+        .map_err(BuildError::Manifest)?;
+    build
+        .write(&out_dir)
+        .map_err(BuildError::RenderBuildOutput)?;
+
+    if args.optimize_size.is_some() {
+        print_sizes(&sizes, &out_dir, &files);
+    }
 
     println!("Build finished");
 
@@ -162,8 +1221,20 @@ pub fn run_build(_global_opts: &GlobalOptions, args: &BuildArgs) -> Result<(), B
 pub enum BuildError {
     Metadata(String),
     CargoBuild,
+    CargoFailed(ExitStatus),
+    Compile(Vec<Diagnostic>),
+    LinkerNotFound(String),
+    StaleBinary(String),
+    Strip(String),
     RenderBuildOutput(String),
-    GenezioManifestNotFound,
+    Sdk(String),
+    NoBinary,
+    AmbiguousBinary(Vec<String>),
+    MissingBinary(String),
+    DuplicateClass(String),
+    Manifest(ManifestError),
+    NoLinker(String),
+    CargoConfig(String),
 }
 
 impl Display for BuildError {
@@ -173,11 +1244,42 @@ impl Display for BuildError {
             "BuildError: {}",
             match self {
                 BuildError::Metadata(text) => format!("failed to get cargo metadata: {text}"),
-                BuildError::GenezioManifestNotFound =>
-                    "genezio.yaml not found in workspace root".to_string(),
+                BuildError::Manifest(err) => err.to_string(),
                 BuildError::CargoBuild => "failed to build with cargo".to_string(),
+                BuildError::CargoFailed(status) =>
+                    format!("cargo exited with {status}, see its output above"),
+                BuildError::Compile(errors) => format!(
+                    "failed to compile, {} error{}:\n{}",
+                    errors.len(),
+                    if errors.len() == 1 { "" } else { "s" },
+                    errors
+                        .iter()
+                        .map(|error| format!("  {error}"))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+                BuildError::Strip(text) => format!("failed to strip the binary: {text}"),
+                BuildError::StaleBinary(text) => format!("refusing to package a stale binary: {text}"),
+                BuildError::LinkerNotFound(linker) => format!(
+                    "linker `{linker}` not found, see `genezio-rs doctor` or pick another with --linker"
+                ),
                 BuildError::RenderBuildOutput(text) =>
                     format!("failed to render build output: {text}"),
+                BuildError::Sdk(text) => format!("failed to generate client sdk: {text}"),
+                BuildError::NoBinary => "cargo did not produce a binary to package".to_string(),
+                BuildError::AmbiguousBinary(names) => format!(
+                    "found several binaries, pick one with --bin: {}",
+                    names.join(", ")
+                ),
+                BuildError::MissingBinary(name) =>
+                    format!("cargo did not produce the `{name}` binary"),
+                BuildError::DuplicateClass(name) =>
+                    format!("class `{name}` is generated by more than one binary"),
+                BuildError::CargoConfig(text) =>
+                    format!("failed to read the cargo configuration: {text}"),
+                BuildError::NoLinker(triple) => format!(
+                    "found no linker for {triple}, see `genezio-rs doctor --target {triple}`"
+                ),
             }
         )
     }
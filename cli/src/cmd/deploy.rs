@@ -1,10 +1,19 @@
 use crate::{
-    cmd::build::{run_build, BuildArgs},
+    build_manifest::BuildManifest,
+    cmd::{
+        build::{run_build, BuildArgs, Compression, Linker, Runtime, SizeLevel, Target},
+        package::{unpack, PackageError},
+    },
     metadata::get_cargo_metadata,
     options::GlobalOptions,
 };
 use clap::Args;
-use std::{error::Error, fmt::Display, process::Command};
+use std::{
+    error::Error,
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use super::build::BuildError;
 
@@ -14,24 +23,96 @@ pub struct DeployArgs {
     #[clap(long, short = 'd')]
     pub debug: bool,
 
+    /// Build with this cargo profile instead of `release`
+    #[clap(long, conflicts_with = "debug")]
+    pub profile: Option<String>,
+
+    /// Features to activate, passed to cargo as they are
+    #[clap(long, short = 'F')]
+    pub features: Vec<String>,
+
+    /// Activate all the features
+    #[clap(long)]
+    pub all_features: bool,
+
+    /// Don't activate the `default` feature
+    #[clap(long)]
+    pub no_default_features: bool,
+
+    /// Optimize the binary for size instead of speed (`z` by default, or `s`), with lto, one
+    /// codegen unit, `panic = "abort"` and stripped symbols on top of the profile. The debug info
+    /// is kept in target/genezio/symbols for `genezio-rs symbolicate`
+    #[clap(long, value_enum, num_args = 0..=1, default_missing_value = "z")]
+    pub optimize_size: Option<SizeLevel>,
+
     /// Clean before building
     #[clap(long, short = 'c')]
     pub clean: bool,
+
+    /// Package to build, for workspaces with several packages
+    #[clap(long, short = 'p')]
+    pub package: Option<String>,
+
+    /// Binary to package, for packages with several binaries
+    #[clap(long)]
+    pub bin: Option<String>,
+
+    /// Where to write the TypeScript client (defaults to target/genezio/sdk/typescript)
+    #[clap(long)]
+    pub ts_sdk_dir: Option<PathBuf>,
+
+    /// Where to write the Rust client crate (defaults to target/genezio/sdk/rust)
+    #[clap(long)]
+    pub rust_sdk_dir: Option<PathBuf>,
+
+    /// How the generated JavaScript class runs the binary
+    #[clap(long, value_enum, default_value_t = Runtime::Bridge)]
+    pub runtime: Runtime,
+
+    /// Compress the binary embedded in index.js (only used with `--runtime trap`)
+    #[clap(long, value_enum, default_value_t = Compression::None)]
+    pub compress: Compression,
+
+    /// What to build the binary for (`host` builds can't be deployed)
+    #[clap(long, value_enum, default_value_t = Target::Aarch64Musl)]
+    pub target: Target,
+
+    /// How to link the binary for the target
+    #[clap(long, value_enum, default_value_t = Linker::Auto)]
+    pub linker: Linker,
+
+    /// Deploy this archive from `genezio-rs package` instead of building, the build options are
+    /// ignored
+    #[clap(long)]
+    pub archive: Option<PathBuf>,
 }
 
 impl From<&DeployArgs> for BuildArgs {
     fn from(args: &DeployArgs) -> Self {
         Self {
             debug: args.debug,
+            profile: args.profile.clone(),
+            features: args.features.clone(),
+            all_features: args.all_features,
+            no_default_features: args.no_default_features,
+            optimize_size: args.optimize_size,
             clean: args.clean,
+            package: args.package.clone(),
+            bin: args.bin.clone(),
+            ts_sdk_dir: args.ts_sdk_dir.clone(),
+            rust_sdk_dir: args.rust_sdk_dir.clone(),
+            runtime: args.runtime,
+            compress: args.compress,
+            target: args.target,
+            linker: args.linker,
         }
     }
 }
 
-fn run_genezio_deploy(metadata: &crate::metadata::CargoMetadata) -> Result<(), DeployError> {
+fn run_genezio_deploy(dir: &Path) -> Result<(), DeployError> {
     let status = Command::new("genezio")
         .arg("deploy")
-        .current_dir(metadata.get_genezio_out_dir())
+        .current_dir(dir)
         .status()
         .map_err(|_| DeployError::Genezio)?;
 
@@ -46,8 +127,41 @@ pub fn run_deploy(global_opts: &GlobalOptions, args: &DeployArgs) -> Result<(),
     println!("Starting deploy");
 
     let metadata = get_cargo_metadata().map_err(|e| DeployError::Metadata(e.to_string()))?;
+
+    if let Some(archive) = &args.archive {
+        let deploy_dir = metadata.get_genezio_deploy_dir();
+        let package = unpack(archive, &deploy_dir).map_err(DeployError::Package)?;
+        println!(
+            "Unpacked {} built for {} from {}{}",
+            archive.display(),
+            package.target,
+            package.git_commit.as_deref().unwrap_or("an unknown commit"),
+            if package.git_dirty {
+                " with changes"
+            } else {
+                ""
+            }
+        );
+
+        run_genezio_deploy(&deploy_dir)?;
+        println!("Deploy finished");
+
+        return Ok(());
+    }
+
+    if args.target == Target::Host {
+        return Err(DeployError::HostTarget);
+    }
+
     run_build(global_opts, &args.into()).map_err(|e| DeployError::BuildError(e))?;
-    run_genezio_deploy(&metadata)?;
+
+    // the build wrote the manifest right before, so this only fails when something else wrote to
+    // the output in between, like another build running at the same time
+    let out_dir = metadata.get_genezio_out_dir();
+    BuildManifest::read(&out_dir)
+        .and_then(|build| build.verify(&out_dir))
+        .map_err(DeployError::StaleBuild)?;
+    run_genezio_deploy(&out_dir)?;
 
     println!("Deploy finished");
 
@@ -58,6 +172,9 @@ pub fn run_deploy(global_opts: &GlobalOptions, args: &DeployArgs) -> Result<(),
 pub enum DeployError {
     Metadata(String),
     BuildError(BuildError),
+    HostTarget,
+    StaleBuild(String),
+    Package(PackageError),
     Genezio,
 }
 
@@ -69,6 +186,11 @@ impl Display for DeployError {
             match self {
                 DeployError::Metadata(text) => format!("failed to get cargo metadata: {text}"),
                 DeployError::BuildError(err) => format!("failed to build: {err}"),
+                DeployError::HostTarget =>
+                    "host builds only run on this machine, pick a Lambda target".to_string(),
+                DeployError::StaleBuild(text) =>
+                    format!("refusing to deploy, the output doesn't match the build: {text}"),
+                DeployError::Package(err) => err.to_string(),
                 DeployError::Genezio => format!("failed to deploy to genezio"),
             }
         )
@@ -0,0 +1,222 @@
+use crate::{
+    artifacts::{read_bin_artifacts, BinArtifact},
+    metadata::{get_cargo_metadata, CargoMetadata},
+    options::GlobalOptions,
+};
+use clap::Args;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::{
+    error::Error,
+    fmt::Display,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::build::{select_artifact, BuildError};
+
+/// How long to wait for more changes before rebuilding, so saving several files rebuilds once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Args)]
+pub struct DevArgs {
+    /// Package to run, for workspaces with several packages
+    #[clap(long, short = 'p')]
+    pub package: Option<String>,
+
+    /// Binary to run, for packages with several binaries
+    #[clap(long)]
+    pub bin: Option<String>,
+
+    /// How long to wait for the server to stop before killing it, in milliseconds
+    #[clap(long, default_value_t = 5000)]
+    pub shutdown_timeout: u64,
+}
+
+/// Builds for the host. Returns `None` when the build fails, cargo already printed why.
+pub fn cargo_build(
+    package: Option<&str>,
+    bin: Option<&str>,
+) -> Result<Option<BinArtifact>, BuildError> {
+    let mut command = Command::new("cargo");
+    command
+        .arg("build")
+        .arg("--message-format=json-render-diagnostics");
+
+    if let Some(package) = package {
+        command.arg("--package").arg(package);
+    }
+
+    if let Some(bin) = bin {
+        command.arg("--bin").arg(bin);
+    }
+
+    let output = command
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|_| BuildError::CargoBuild)?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    select_artifact(read_bin_artifacts(&output.stdout), bin).map(Some)
+}
+
+fn watch_paths(metadata: &CargoMetadata) -> Vec<(PathBuf, RecursiveMode)> {
+    let mut paths = vec![(
+        metadata.workspace_root.join("Cargo.toml"),
+        RecursiveMode::NonRecursive,
+    )];
+
+    for package in &metadata.packages {
+        paths.push((package.manifest_path.clone(), RecursiveMode::NonRecursive));
+
+        if let Some(dir) = package.manifest_path.parent() {
+            paths.push((dir.join("src"), RecursiveMode::Recursive));
+        }
+    }
+
+    paths.sort();
+    paths.dedup();
+    paths.retain(|(path, _)| path.exists());
+    paths
+}
+
+/// Blocks until something changes and returns the changed paths.
+fn wait_for_changes(
+    receiver: &Receiver<notify::Result<notify::Event>>,
+) -> Result<Vec<PathBuf>, DevError> {
+    let mut changed = Vec::new();
+
+    loop {
+        let event = if changed.is_empty() {
+            receiver
+                .recv()
+                .map_err(|e| DevError::Watch(e.to_string()))?
+        } else {
+            match receiver.recv_timeout(DEBOUNCE) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => return Ok(changed),
+                Err(e) => return Err(DevError::Watch(e.to_string())),
+            }
+        };
+
+        let event = event.map_err(|e| DevError::Watch(e.to_string()))?;
+        if matches!(event.kind, EventKind::Access(_)) {
+            continue;
+        }
+
+        for path in event.paths {
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+    }
+}
+
+fn start_server(artifact: &BinArtifact) -> Result<Child, DevError> {
+    Command::new(&artifact.executable)
+        .spawn()
+        .map_err(|e| DevError::Server(e.to_string()))
+}
+
+/// Asks the server to stop with `SIGTERM` and kills it if it's still running after `timeout`.
+fn stop_server(mut child: Child, timeout: Duration) -> Result<(), DevError> {
+    if child
+        .try_wait()
+        .map_err(|e| DevError::Server(e.to_string()))?
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+    }
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if child
+            .try_wait()
+            .map_err(|e| DevError::Server(e.to_string()))?
+            .is_some()
+        {
+            return Ok(());
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    println!("Server did not stop in time, killing it");
+    child.kill().map_err(|e| DevError::Server(e.to_string()))?;
+    child.wait().map_err(|e| DevError::Server(e.to_string()))?;
+
+    Ok(())
+}
+
+pub fn run_dev(_global_opts: &GlobalOptions, args: &DevArgs) -> Result<(), DevError> {
+    println!("Starting dev server");
+
+    let metadata = get_cargo_metadata().map_err(|e| DevError::Metadata(e.to_string()))?;
+    let timeout = Duration::from_millis(args.shutdown_timeout);
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).map_err(|e| DevError::Watch(e.to_string()))?;
+
+    for (path, mode) in watch_paths(&metadata) {
+        watcher
+            .watch(&path, mode)
+            .map_err(|e| DevError::Watch(e.to_string()))?;
+    }
+
+    let mut server = None;
+
+    loop {
+        match cargo_build(args.package.as_deref(), args.bin.as_deref()).map_err(DevError::Build)? {
+            Some(artifact) => {
+                if let Some(child) = server.take() {
+                    println!("Restarting server");
+                    stop_server(child, timeout)?;
+                }
+
+                server = Some(start_server(&artifact)?);
+            }
+            None => println!("Build failed, waiting for changes"),
+        }
+
+        let changed = wait_for_changes(&receiver)?;
+        for path in &changed {
+            let path = path.strip_prefix(&metadata.workspace_root).unwrap_or(path);
+            println!("Changed {}", path.display());
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DevError {
+    Metadata(String),
+    Watch(String),
+    Build(BuildError),
+    Server(String),
+}
+
+impl Display for DevError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "DevError: {}",
+            match self {
+                DevError::Metadata(text) => format!("failed to get cargo metadata: {text}"),
+                DevError::Watch(text) => format!("failed to watch for changes: {text}"),
+                DevError::Build(err) => err.to_string(),
+                DevError::Server(text) => format!("failed to run the server: {text}"),
+            }
+        )
+    }
+}
+
+impl Error for DevError {}
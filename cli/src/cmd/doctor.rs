@@ -1,22 +1,67 @@
 use crate::options::GlobalOptions;
 use clap::Args;
-use std::{error::Error, fmt::Display, process::Command};
+use std::{
+    error::Error,
+    fmt::Display,
+    process::{Command, Stdio},
+};
+
+use super::build::{Linker, Target};
 
 const HELP_RUSTUP: &'static str = "make sure you have rustup installed: https://rustup.rs/";
 
 const HELP_CARGO: &'static str =
     "make sure you have rust and cargo installed (using rustup): https://rustup.rs/";
 
-const HELP_RUSTUP_AARCH64_MUSL_TARGET: &'static str =
-    "make sure you have the target available.\ninsall it with: `rustup target add aarch64-unknown-linux-musl`";
-
-const HELP_GNU_AARCH64_MUSL_TOOLCHAIN: &'static str =
-    "make sure you have the toolchain installed. more help here: https://github.com/laurci/genezio-rs";
+const HELP_LINKER: &'static str =
+    "install one of them (more help here: https://github.com/laurci/genezio-rs):
+  gcc: the gcc cross toolchain for the target
+  zig: zig (https://ziglang.org/) and `cargo install cargo-zigbuild`
+  cross: docker or podman and `cargo install cross`";
 
 const HELP_GENEZIO: &'static str = "make sure you have genezio installed: https://genez.io/";
 
 #[derive(Debug, Args)]
-pub struct DoctorArgs {}
+pub struct DoctorArgs {
+    /// Target to check the toolchain for
+    #[clap(long, value_enum, default_value_t = Target::Aarch64Musl)]
+    pub target: Target,
+}
+
+/// Whether `program` runs and exits successfully with `args`.
+pub fn command_works(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Whether `linker` can link binaries for `target` on this machine.
+pub fn linker_available(linker: Linker, target: Target) -> bool {
+    match linker {
+        Linker::Auto => false,
+        Linker::Gcc => target
+            .gcc()
+            .is_some_and(|gcc| command_works(gcc, &["--version"])),
+        Linker::Zig => {
+            command_works("zig", &["version"]) && command_works("cargo", &["zigbuild", "--version"])
+        }
+        Linker::Cross => {
+            command_works("cross", &["--version"])
+                && (command_works("docker", &["--version"])
+                    || command_works("podman", &["--version"]))
+        }
+    }
+}
+
+/// The linker `--linker auto` builds for `target` with.
+pub fn find_linker(target: Target) -> Option<Linker> {
+    [Linker::Gcc, Linker::Zig, Linker::Cross]
+        .into_iter()
+        .find(|linker| linker_available(*linker, target))
+}
 
 fn check_unix_based_os() -> Result<(), DoctorError> {
     if !cfg!(target_os = "linux") && !cfg!(target_os = "macos") {
@@ -60,39 +105,45 @@ fn check_cargo() -> Result<(), DoctorError> {
     Ok(())
 }
 
-fn check_rustup_aarch64_musl_target() -> Result<(), DoctorError> {
+fn check_rustup_target(triple: &str) -> Result<(), DoctorError> {
     let output = Command::new("rustup")
         .arg("target")
         .arg("list")
         .arg("--installed")
         .output()
-        .map_err(|_| DoctorError::RustupAarch64MuslTarget)?;
+        .map_err(|_| DoctorError::RustupTarget(triple.to_owned()))?;
 
     let text = String::from_utf8_lossy(&output.stdout);
     let toolchains = text.split('\n').collect::<Vec<&str>>();
 
-    if !toolchains.contains(&"aarch64-unknown-linux-musl") {
-        return Err(DoctorError::RustupAarch64MuslTarget);
+    if !toolchains.contains(&triple) {
+        return Err(DoctorError::RustupTarget(triple.to_owned()));
     }
 
-    println!("target aarch64-unknown-linux-musl: ok");
+    println!("target {triple}: ok");
 
     Ok(())
 }
 
-fn check_gnu_aarch64_musl_toolchain() -> Result<(), DoctorError> {
-    let status = Command::new("aarch64-linux-gnu-gcc")
-        .arg("--version")
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .status()
-        .map_err(|_| DoctorError::GnuAarch64MuslToolchain)?;
-
-    if !status.success() {
-        return Err(DoctorError::GnuAarch64MuslToolchain);
+fn check_linkers(target: Target, triple: &str) -> Result<(), DoctorError> {
+    let mut found = false;
+    for linker in [Linker::Gcc, Linker::Zig, Linker::Cross] {
+        let available = linker_available(linker, target);
+        found |= available;
+
+        let name = match (linker, target.gcc()) {
+            (Linker::Gcc, Some(gcc)) => format!("gcc ({gcc})"),
+            _ => linker.as_str().to_owned(),
+        };
+        println!(
+            "linker {name}: {}",
+            if available { "ok" } else { "not found" }
+        );
     }
 
-    println!("toolchain aarch64-linux-musl-gnu: ok");
+    if !found {
+        return Err(DoctorError::Linker(triple.to_owned()));
+    }
 
     Ok(())
 }
@@ -114,14 +165,16 @@ fn check_genezio() -> Result<(), DoctorError> {
     Ok(())
 }
 
-pub fn run_doctor(_global_opts: &GlobalOptions, _args: &DoctorArgs) -> Result<(), DoctorError> {
+pub fn run_doctor(_global_opts: &GlobalOptions, args: &DoctorArgs) -> Result<(), DoctorError> {
     println!("Running doctor");
 
     check_unix_based_os()?;
     check_rustup()?;
     check_cargo()?;
-    check_rustup_aarch64_musl_target()?;
-    check_gnu_aarch64_musl_toolchain()?;
+    if let Some(triple) = args.target.triple() {
+        check_rustup_target(triple)?;
+        check_linkers(args.target, triple)?;
+    }
     check_genezio()?;
 
     Ok(())
@@ -132,8 +185,8 @@ pub enum DoctorError {
     OS,
     Rustup,
     Cargo,
-    RustupAarch64MuslTarget,
-    GnuAarch64MuslToolchain,
+    RustupTarget(String),
+    Linker(String),
     Genezio,
 }
 
@@ -146,14 +199,11 @@ impl Display for DoctorError {
                 DoctorError::OS => "Only Linux and MacOS are supported".to_owned(),
                 DoctorError::Rustup => format!("rustup not found.\nHELP: {}", HELP_RUSTUP),
                 DoctorError::Cargo => format!("cargo not found.\nHELP: {}", HELP_CARGO),
-                DoctorError::RustupAarch64MuslTarget => format!(
-                    "aarch64-unknown-linux-musl target not found.\nHELP: {}",
-                    HELP_RUSTUP_AARCH64_MUSL_TARGET
-                ),
-                DoctorError::GnuAarch64MuslToolchain => format!(
-                    "aarch64-linux-musl-gnu toolchain not found.\nHELP: {}",
-                    HELP_GNU_AARCH64_MUSL_TOOLCHAIN
+                DoctorError::RustupTarget(triple) => format!(
+                    "{triple} target not found.\nHELP: make sure you have the target available.\ninsall it with: `rustup target add {triple}`"
                 ),
+                DoctorError::Linker(triple) =>
+                    format!("no linker found for {triple}.\nHELP: {}", HELP_LINKER),
                 DoctorError::Genezio => format!("genezio not found.\nHELP: {}", HELP_GENEZIO),
             }
         )
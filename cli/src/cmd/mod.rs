@@ -2,10 +2,15 @@ use clap::Subcommand;
 
 use crate::options::GlobalOptions;
 
+mod bench;
 mod build;
 mod deploy;
+mod dev;
 mod doctor;
 mod new;
+mod package;
+mod run;
+mod symbolicate;
 
 #[derive(Debug, Subcommand)]
 pub enum Command {
@@ -15,9 +20,24 @@ pub enum Command {
     /// Build the project
     Build(build::BuildArgs),
 
+    /// Archive the output of the last build into a reproducible zip to deploy later
+    Package(package::PackageArgs),
+
     /// Deploy the project to genezio
     Deploy(deploy::DeployArgs),
 
+    /// Run the project locally, rebuilding and restarting it on changes
+    Dev(dev::DevArgs),
+
+    /// Build and run the project locally, optionally the way it runs on genezio
+    Run(run::RunArgs),
+
+    /// Measure the project's performance
+    Bench(bench::BenchArgs),
+
+    /// Resolve the addresses of a backtrace from the logs with the debug info of the build
+    Symbolicate(symbolicate::SymbolicateArgs),
+
     /// Verify all dependencies
     Doctor(doctor::DoctorArgs),
 }
@@ -27,7 +47,14 @@ impl Command {
         match self {
             Command::New(args) => new::run_new(global_opts, args).map_err(|e| e.into()),
             Command::Build(args) => build::run_build(global_opts, args).map_err(|e| e.into()),
+            Command::Package(args) => package::run_package(global_opts, args).map_err(|e| e.into()),
             Command::Deploy(args) => deploy::run_deploy(global_opts, args).map_err(|e| e.into()),
+            Command::Dev(args) => dev::run_dev(global_opts, args).map_err(|e| e.into()),
+            Command::Run(args) => run::run_run(global_opts, args).map_err(|e| e.into()),
+            Command::Bench(args) => bench::run_bench(global_opts, args).map_err(|e| e.into()),
+            Command::Symbolicate(args) => {
+                symbolicate::run_symbolicate(global_opts, args).map_err(|e| e.into())
+            }
             Command::Doctor(args) => doctor::run_doctor(global_opts, args).map_err(|e| e.into()),
         }
     }
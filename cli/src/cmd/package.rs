@@ -0,0 +1,202 @@
+use crate::{
+    archive::{read_zip, write_zip, ArchiveFile},
+    build_manifest::{sha256, BuildManifest},
+    cmd::build::Target,
+    metadata::get_cargo_metadata,
+    options::GlobalOptions,
+};
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::Display,
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Component, Path, PathBuf},
+};
+
+/// Name of the manifest in the archive.
+pub const MANIFEST_NAME: &str = "package-manifest.json";
+
+#[derive(Debug, Args)]
+pub struct PackageArgs {
+    /// Where to write the archive (defaults to target/genezio/package.zip)
+    #[clap(long, short = 'o')]
+    pub output: Option<PathBuf>,
+}
+
+/// What a package holds. Unlike the build manifest it has nothing specific to the machine or the
+/// time of the build, so packaging the same build output always gives the same archive.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackageManifest {
+    pub git_commit: Option<String>,
+    pub git_dirty: bool,
+    pub target: String,
+    pub profile: String,
+    /// The sha256 of every file in the archive but this one, by path.
+    pub files: BTreeMap<String, String>,
+}
+
+/// Unix permissions for a file in the archive. Only whether it's executable is kept, so the umask
+/// of the machine that built it doesn't end up in the archive.
+fn archive_mode(path: &Path) -> Result<u32, String> {
+    let mode = fs::metadata(path)
+        .map_err(|e| format!("{}: {e}", path.display()))?
+        .permissions()
+        .mode();
+
+    Ok(if mode & 0o111 != 0 { 0o755 } else { 0o644 })
+}
+
+/// Archives the output of the last build, after checking it wasn't changed since.
+pub fn package(out_dir: &Path) -> Result<Vec<u8>, PackageError> {
+    let build = BuildManifest::read(out_dir).map_err(PackageError::NoBuild)?;
+    build.verify(out_dir).map_err(PackageError::StaleBuild)?;
+
+    let deployable = Target::value_variants()
+        .iter()
+        .any(|target| target.triple() == Some(build.target.as_str()));
+    if !deployable {
+        return Err(PackageError::HostTarget(build.target));
+    }
+
+    let mut files = Vec::new();
+    for name in build.files.keys() {
+        let path = out_dir.join(name);
+        let bytes = fs::read(&path).map_err(|e| PackageError::Io(format!("{name}: {e}")))?;
+        files.push(ArchiveFile {
+            name: name.clone(),
+            mode: archive_mode(&path).map_err(PackageError::Io)?,
+            bytes,
+        });
+    }
+
+    let manifest = PackageManifest {
+        git_commit: build.git_commit,
+        git_dirty: build.git_dirty,
+        target: build.target,
+        profile: build.profile,
+        files: build.files,
+    };
+    files.push(ArchiveFile {
+        name: MANIFEST_NAME.to_owned(),
+        mode: 0o644,
+        bytes: serde_json::to_vec_pretty(&manifest).map_err(|e| PackageError::Io(e.to_string()))?,
+    });
+
+    write_zip(&files).map_err(PackageError::Io)
+}
+
+/// Extracts a package to `dir`, replacing what was there, after checking every file against its
+/// manifest.
+pub fn unpack(archive: &Path, dir: &Path) -> Result<PackageManifest, PackageError> {
+    let bytes =
+        fs::read(archive).map_err(|e| PackageError::Io(format!("{}: {e}", archive.display())))?;
+    let mut files = read_zip(&bytes).map_err(PackageError::Archive)?;
+
+    let index = files
+        .iter()
+        .position(|file| file.name == MANIFEST_NAME)
+        .ok_or_else(|| PackageError::Archive(format!("{MANIFEST_NAME} is missing")))?;
+    let manifest = serde_json::from_slice::<PackageManifest>(&files.remove(index).bytes)
+        .map_err(|e| PackageError::Archive(format!("{MANIFEST_NAME}: {e}")))?;
+
+    let mut expected = manifest.files.clone();
+    for file in &files {
+        match expected.remove(&file.name) {
+            Some(hash) if hash == sha256(&file.bytes) => {}
+            Some(_) => return Err(PackageError::Mismatch(format!("{} changed", file.name))),
+            None => {
+                return Err(PackageError::Mismatch(format!(
+                    "{} is not listed",
+                    file.name
+                )))
+            }
+        }
+
+        // keep everything inside `dir`
+        if Path::new(&file.name)
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(PackageError::Archive(format!(
+                "{} is outside the archive",
+                file.name
+            )));
+        }
+    }
+
+    if let Some(name) = expected.keys().next() {
+        return Err(PackageError::Mismatch(format!("{name} is missing")));
+    }
+
+    if dir.exists() {
+        fs::remove_dir_all(dir).map_err(|e| PackageError::Io(e.to_string()))?;
+    }
+
+    for file in files {
+        let path = dir.join(&file.name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| PackageError::Io(e.to_string()))?;
+        }
+
+        fs::write(&path, &file.bytes).map_err(|e| PackageError::Io(e.to_string()))?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(file.mode))
+            .map_err(|e| PackageError::Io(e.to_string()))?;
+    }
+
+    Ok(manifest)
+}
+
+pub fn run_package(_global_opts: &GlobalOptions, args: &PackageArgs) -> Result<(), PackageError> {
+    let metadata = get_cargo_metadata().map_err(|e| PackageError::Metadata(e.to_string()))?;
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| metadata.get_genezio_package_path());
+
+    let archive = package(&metadata.get_genezio_out_dir())?;
+    fs::write(&output, &archive)
+        .map_err(|e| PackageError::Io(format!("{}: {e}", output.display())))?;
+
+    println!("Packaged {}", output.display());
+    println!("sha256 {}", sha256(&archive));
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum PackageError {
+    Metadata(String),
+    NoBuild(String),
+    StaleBuild(String),
+    HostTarget(String),
+    Io(String),
+    Archive(String),
+    Mismatch(String),
+}
+
+impl Display for PackageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PackageError: {}",
+            match self {
+                PackageError::Metadata(text) => format!("failed to get cargo metadata: {text}"),
+                PackageError::NoBuild(text) =>
+                    format!("failed to read the build manifest, run `genezio-rs build` first: {text}"),
+                PackageError::StaleBuild(text) =>
+                    format!("refusing to package, the output doesn't match the build: {text}"),
+                PackageError::HostTarget(triple) =>
+                    format!("the last build is for {triple}, which only runs on this machine, pick a Lambda target"),
+                PackageError::Io(text) => format!("failed to access the package files: {text}"),
+                PackageError::Archive(text) => format!("failed to read the archive: {text}"),
+                PackageError::Mismatch(text) =>
+                    format!("the archive doesn't match its manifest: {text}"),
+            }
+        )
+    }
+}
+
+impl Error for PackageError {}
@@ -0,0 +1,232 @@
+use crate::{
+    emulator::{
+        api_gateway_event, function_url_event, read_function_url_response, read_request,
+        write_response, Emulator, HttpRequest, HttpResponse, Outcome,
+    },
+    metadata::get_cargo_metadata,
+    options::GlobalOptions,
+};
+use clap::{Args, ValueEnum};
+use std::{
+    error::Error,
+    fmt::Display,
+    io::BufReader,
+    net::{TcpListener, TcpStream},
+    process::{Command, ExitStatus},
+    sync::Arc,
+    thread,
+    time::Instant,
+};
+
+use super::{
+    build::{cargo_build_host_lambda, BuildError},
+    dev,
+};
+
+/// Response headers the local server sets itself.
+const HOP_HEADERS: &[&str] = &["connection", "content-length", "transfer-encoding"];
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// Package to run, for workspaces with several packages
+    #[clap(long, short = 'p')]
+    pub package: Option<String>,
+
+    /// Binary to run, for packages with several binaries
+    #[clap(long)]
+    pub bin: Option<String>,
+
+    /// Run the app the way it runs on genezio, behind a local emulator of the Lambda runtime API
+    /// that turns HTTP requests into Lambda events
+    #[clap(long)]
+    pub lambda: bool,
+
+    /// Event the requests are turned into with --lambda
+    #[clap(long, value_enum, default_value_t = Event::FunctionUrl, requires = "lambda")]
+    pub event: Event,
+
+    /// Address to listen on (defaults to the `HOST` the app uses, or 127.0.0.1 with --lambda)
+    #[clap(long)]
+    pub host: Option<String>,
+
+    /// Port to listen on (defaults to the `PORT` the app uses, or 3000 with --lambda)
+    #[clap(long)]
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Event {
+    /// A Lambda Function URL event (payload version 2.0), what genezio sends
+    FunctionUrl,
+    /// An API Gateway REST API proxy event (payload version 1.0)
+    ApiGateway,
+}
+
+impl Event {
+    fn build(self, request: &HttpRequest, source_ip: &str) -> Vec<u8> {
+        match self {
+            Event::FunctionUrl => function_url_event(request, source_ip),
+            Event::ApiGateway => api_gateway_event(request, source_ip),
+        }
+    }
+}
+
+/// Sends one request through the emulator and writes back what the app answered.
+fn forward(emulator: &Emulator, event: Event, mut stream: TcpStream) -> Result<(), Box<dyn Error>> {
+    let source_ip = stream.peer_addr()?.ip().to_string();
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let Some(request) = read_request(&mut reader)? else {
+        return Ok(());
+    };
+
+    let start = Instant::now();
+    let outcome = emulator.invoke(event.build(&request, &source_ip)).recv();
+
+    let response = match outcome {
+        Ok(Outcome::Response(payload)) => read_function_url_response(&payload),
+        Ok(Outcome::Error(error)) => {
+            println!(
+                "{} {}: the invocation failed: {}",
+                request.method,
+                request.path,
+                String::from_utf8_lossy(&error)
+            );
+            HttpResponse {
+                status: 502,
+                headers: vec![("Content-Type".to_owned(), "application/json".to_owned())],
+                body: error,
+            }
+        }
+        Err(_) => HttpResponse {
+            status: 502,
+            headers: vec![],
+            body: b"the Lambda emulator stopped".to_vec(),
+        },
+    };
+
+    println!(
+        "{} {} {} in {} ms",
+        request.method,
+        request.path,
+        response.status,
+        start.elapsed().as_millis()
+    );
+
+    let mut headers = response
+        .headers
+        .iter()
+        .filter(|(name, _)| !HOP_HEADERS.contains(&name.to_ascii_lowercase().as_str()))
+        .map(|(name, value)| (name.as_str(), value.clone()))
+        .collect::<Vec<_>>();
+    headers.push(("Connection", "close".to_owned()));
+
+    // the reason phrase can be left out, but not the space before it
+    write_response(
+        &mut stream,
+        &format!("{} ", response.status),
+        &headers,
+        &response.body,
+    )?;
+
+    Ok(())
+}
+
+fn run_lambda(args: &RunArgs) -> Result<ExitStatus, RunError> {
+    let metadata = get_cargo_metadata().map_err(|e| RunError::Metadata(e.to_string()))?;
+
+    println!("Building for the host with the Lambda runtime");
+    let artifact = cargo_build_host_lambda(&metadata, args.package.as_deref(), args.bin.as_deref())
+        .map_err(RunError::Build)?;
+
+    let emulator = Arc::new(Emulator::start().map_err(|e| RunError::Emulator(e.to_string()))?);
+
+    let host = args.host.as_deref().unwrap_or("127.0.0.1");
+    let port = args.port.unwrap_or(3000);
+    let listener = TcpListener::bind((host, port)).map_err(|e| RunError::Listen(e.to_string()))?;
+    let address = listener
+        .local_addr()
+        .map_err(|e| RunError::Listen(e.to_string()))?;
+
+    let mut child = Command::new(&artifact.executable)
+        .envs(emulator.env())
+        .spawn()
+        .map_err(|e| RunError::Server(e.to_string()))?;
+
+    println!("listening on {address}, through the Lambda emulator");
+
+    let server_emulator = emulator.clone();
+    let event = args.event;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let emulator = server_emulator.clone();
+            thread::spawn(move || {
+                if let Err(e) = forward(&emulator, event, stream) {
+                    println!("failed to answer a request: {e}");
+                }
+            });
+        }
+    });
+
+    child.wait().map_err(|e| RunError::Server(e.to_string()))
+}
+
+fn run_host(args: &RunArgs) -> Result<ExitStatus, RunError> {
+    let artifact = dev::cargo_build(args.package.as_deref(), args.bin.as_deref())
+        .map_err(RunError::Build)?
+        .ok_or(RunError::Build(BuildError::CargoBuild))?;
+
+    let mut command = Command::new(&artifact.executable);
+    if let Some(host) = &args.host {
+        command.env("HOST", host);
+    }
+    if let Some(port) = args.port {
+        command.env("PORT", port.to_string());
+    }
+
+    command
+        .status()
+        .map_err(|e| RunError::Server(e.to_string()))
+}
+
+pub fn run_run(_global_opts: &GlobalOptions, args: &RunArgs) -> Result<(), RunError> {
+    let status = if args.lambda {
+        run_lambda(args)?
+    } else {
+        run_host(args)?
+    };
+
+    if !status.success() {
+        return Err(RunError::Exited(status));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum RunError {
+    Metadata(String),
+    Build(BuildError),
+    Emulator(String),
+    Listen(String),
+    Server(String),
+    Exited(ExitStatus),
+}
+
+impl Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RunError: {}",
+            match self {
+                RunError::Metadata(text) => format!("failed to get cargo metadata: {text}"),
+                RunError::Build(err) => err.to_string(),
+                RunError::Emulator(text) => format!("failed to start the emulator: {text}"),
+                RunError::Listen(text) => format!("failed to listen: {text}"),
+                RunError::Server(text) => format!("failed to run the app: {text}"),
+                RunError::Exited(status) => format!("the app exited with {status}"),
+            }
+        )
+    }
+}
+
+impl Error for RunError {}
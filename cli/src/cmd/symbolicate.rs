@@ -0,0 +1,264 @@
+use crate::{build_manifest::BuildManifest, metadata::get_cargo_metadata, options::GlobalOptions};
+use addr2line::Loader;
+use clap::Args;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Display,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+/// Prefix of the line `genezio::panic` prints before a panic.
+const PANIC_PREFIX: &str = "genezio:panic ";
+
+/// Where the frames std prints start their names, the `at` lines go 4 columns further.
+const NAME_COLUMN: usize = 27;
+
+#[derive(Debug, Args)]
+pub struct SymbolicateArgs {
+    /// Logs with the backtrace, printed with `RUST_BACKTRACE=full` (defaults to stdin)
+    pub file: Option<PathBuf>,
+
+    /// Build id of the binary that panicked, instead of the one in the `genezio:panic` line
+    #[clap(long)]
+    pub build_id: Option<String>,
+
+    /// How far from its link time addresses the binary was loaded, in hex, instead of the one in
+    /// the `genezio:panic` line
+    #[clap(long, value_parser = parse_hex)]
+    pub bias: Option<u64>,
+
+    /// Binary that panicked, for builds with several binaries and logs without a `genezio:panic`
+    /// line
+    #[clap(long)]
+    pub bin: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PanicLine {
+    build_id: String,
+    bias: String,
+}
+
+fn parse_hex(text: &str) -> Result<u64, String> {
+    u64::from_str_radix(text.trim_start_matches("0x"), 16).map_err(|e| e.to_string())
+}
+
+/// The binary the frames that follow come from.
+#[derive(Debug, Clone)]
+struct Binary {
+    build_id: String,
+    bias: u64,
+}
+
+/// A frame std printed without a name, like `  3:     0x5612f0a3c1d2 - <unknown>`. Returns the
+/// line up to the address and the address.
+fn unknown_frame(line: &str) -> Option<(&str, u64)> {
+    let (head, name) = line.split_once(" - ")?;
+    if name.trim() != "<unknown>" {
+        return None;
+    }
+
+    let (index, address) = head.trim_end().rsplit_once(char::is_whitespace)?;
+    index.trim_end().strip_suffix(':')?;
+
+    Some((head, parse_hex(address.strip_prefix("0x")?).ok()?))
+}
+
+/// The debug info `genezio-rs build --optimize-size` kept for `build_id`.
+fn debug_file(symbols_dir: &Path, build_id: &str) -> Result<PathBuf, SymbolicateError> {
+    let dir = symbols_dir.join(build_id);
+    let entries =
+        fs::read_dir(&dir).map_err(|_| SymbolicateError::NoSymbols(build_id.to_owned()))?;
+
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "debug")
+        })
+        .ok_or_else(|| SymbolicateError::NoSymbols(build_id.to_owned()))
+}
+
+/// The binary of the last build, for logs without a `genezio:panic` line.
+fn last_build(out_dir: &Path, bin: Option<&str>) -> Result<String, SymbolicateError> {
+    let build = BuildManifest::read(out_dir).map_err(SymbolicateError::Manifest)?;
+    let mut binaries = build
+        .binaries
+        .iter()
+        .filter(|binary| bin.is_none_or(|bin| binary.name == bin))
+        .collect::<Vec<_>>();
+
+    if binaries.len() != 1 {
+        return Err(SymbolicateError::NoBuildId);
+    }
+
+    binaries
+        .remove(0)
+        .build_id
+        .clone()
+        .ok_or(SymbolicateError::NoBuildId)
+}
+
+/// Prints the frames at `address`, innermost first, in the layout std uses.
+fn print_frames(
+    loader: &Loader,
+    head: &str,
+    address: u64,
+    bias: u64,
+) -> Result<(), SymbolicateError> {
+    // the addresses are where the calls return to, the call is the instruction before
+    let probe = address.wrapping_sub(bias).wrapping_sub(1);
+    let mut frames = loader
+        .find_frames(probe)
+        .map_err(|e| SymbolicateError::Symbols(e.to_string()))?;
+
+    let indent = NAME_COLUMN.max(head.len() + 3);
+    let symbol = || {
+        loader
+            .find_symbol(probe)
+            .map(|symbol| addr2line::demangle_auto(symbol.into(), None).into_owned())
+    };
+
+    let mut first = true;
+    while let Some(frame) = frames
+        .next()
+        .map_err(|e| SymbolicateError::Symbols(e.to_string()))?
+    {
+        let name = frame
+            .function
+            .as_ref()
+            .and_then(|function| function.demangle().ok())
+            .map(|name| name.into_owned())
+            .or_else(symbol)
+            .unwrap_or_else(|| "<unknown>".to_owned());
+
+        if first {
+            println!("{head} - {name}");
+            first = false;
+        } else {
+            println!("{:indent$}{name}", "");
+        }
+
+        if let Some(location) = frame.location {
+            let mut at = location.file.unwrap_or("<unknown>").to_owned();
+            for number in [location.line, location.column].into_iter().flatten() {
+                at.push_str(&format!(":{number}"));
+            }
+            println!("{:1$}at {at}", "", indent + 4);
+        }
+    }
+
+    if first {
+        println!(
+            "{head} - {}",
+            symbol().unwrap_or_else(|| "<unknown>".to_owned())
+        );
+    }
+
+    Ok(())
+}
+
+pub fn run_symbolicate(
+    _global_opts: &GlobalOptions,
+    args: &SymbolicateArgs,
+) -> Result<(), SymbolicateError> {
+    let mut text = String::new();
+    match &args.file {
+        Some(path) => {
+            text = fs::read_to_string(path)
+                .map_err(|e| SymbolicateError::Read(format!("{}: {e}", path.display())))?
+        }
+        None => {
+            io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|e| SymbolicateError::Read(e.to_string()))?;
+        }
+    }
+
+    let metadata = get_cargo_metadata().map_err(|e| SymbolicateError::Metadata(e.to_string()))?;
+    let symbols_dir = metadata.get_genezio_symbols_dir();
+
+    let mut binary = args.build_id.clone().map(|build_id| Binary {
+        build_id,
+        bias: args.bias.unwrap_or_default(),
+    });
+    let mut loaders = HashMap::<String, Loader>::new();
+
+    for line in text.lines() {
+        if let Some((_, json)) = line.split_once(PANIC_PREFIX) {
+            if let Ok(panic) = serde_json::from_str::<PanicLine>(json.trim()) {
+                binary = Some(Binary {
+                    build_id: args.build_id.clone().unwrap_or(panic.build_id),
+                    bias: match args.bias {
+                        Some(bias) => bias,
+                        None => parse_hex(&panic.bias).map_err(SymbolicateError::Read)?,
+                    },
+                });
+            }
+
+            println!("{line}");
+            continue;
+        }
+
+        let Some((head, address)) = unknown_frame(line) else {
+            println!("{line}");
+            continue;
+        };
+
+        let binary = match &binary {
+            Some(binary) => binary,
+            None => binary.insert(Binary {
+                build_id: last_build(&metadata.get_genezio_out_dir(), args.bin.as_deref())?,
+                bias: args.bias.unwrap_or_default(),
+            }),
+        };
+
+        if !loaders.contains_key(&binary.build_id) {
+            let path = debug_file(&symbols_dir, &binary.build_id)?;
+            let loader = Loader::new(&path)
+                .map_err(|e| SymbolicateError::Symbols(format!("{}: {e}", path.display())))?;
+            loaders.insert(binary.build_id.clone(), loader);
+        }
+
+        print_frames(&loaders[&binary.build_id], head, address, binary.bias)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum SymbolicateError {
+    Metadata(String),
+    Read(String),
+    Manifest(String),
+    NoBuildId,
+    NoSymbols(String),
+    Symbols(String),
+}
+
+impl Display for SymbolicateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "SymbolicateError: {}",
+            match self {
+                SymbolicateError::Metadata(text) => format!("failed to get cargo metadata: {text}"),
+                SymbolicateError::Read(text) => format!("failed to read the backtrace: {text}"),
+                SymbolicateError::Manifest(text) =>
+                    format!("failed to read the build manifest: {text}"),
+                SymbolicateError::NoBuildId => "the logs have no `genezio:panic` line and the last build has no single binary with a build id, pass --build-id".to_string(),
+                SymbolicateError::NoSymbols(build_id) => format!(
+                    "no debug info for build {build_id} in target/genezio/symbols, it is kept by `genezio-rs build --optimize-size`"
+                ),
+                SymbolicateError::Symbols(text) => format!("failed to read the debug info: {text}"),
+            }
+        )
+    }
+}
+
+impl Error for SymbolicateError {}
@@ -0,0 +1,858 @@
+//! A local stand-in for the Lambda runtime and extensions APIs, just enough to run a binary built
+//! for Lambda and send it Function URL or API Gateway events.
+
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const FUNCTION_NAME: &str = "genezio-local";
+
+/// The longest a Lambda function can run, used as the deadline of every invocation.
+const DEADLINE: Duration = Duration::from_secs(900);
+
+/// How the runtime answered an invocation.
+#[derive(Debug)]
+pub enum Outcome {
+    Response(Vec<u8>),
+    /// The handler failed, or the runtime failed to initialize, with the error it posted.
+    Error(Vec<u8>),
+}
+
+struct Invocation {
+    id: String,
+    event: Vec<u8>,
+}
+
+struct State {
+    invocations: Mutex<Receiver<Invocation>>,
+    pending: Mutex<HashMap<String, Sender<Outcome>>>,
+    shutdown: Mutex<bool>,
+    shutdown_changed: Condvar,
+}
+
+pub struct Emulator {
+    address: SocketAddr,
+    state: Arc<State>,
+    invocations: Option<Sender<Invocation>>,
+    next_id: AtomicU64,
+}
+
+/// A request read from an HTTP/1.1 connection.
+pub struct HttpRequest {
+    pub method: String,
+    /// The path with the query string.
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// An HTTP response decoded from what the runtime answered to a Function URL event.
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    Ok(line.trim_end().to_owned())
+}
+
+fn read_chunked(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+
+    loop {
+        let size = read_line(reader)?;
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size"))?;
+
+        if size == 0 {
+            // skip the trailers
+            while !read_line(reader)?.is_empty() {}
+            return Ok(body);
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        read_line(reader)?;
+    }
+}
+
+/// Reads the next request of a connection, `None` once the client closed it.
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Option<HttpRequest>> {
+    let request_line = read_line(reader)?;
+    if request_line.is_empty() {
+        return Ok(None);
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let path = parts.next().unwrap_or_default().to_owned();
+
+    let mut headers = Vec::new();
+    let mut length = 0;
+    let mut chunked = false;
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => length = value.parse().unwrap_or(0),
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            _ => {}
+        }
+        headers.push((name.trim().to_owned(), value.to_owned()));
+    }
+
+    let body = if chunked {
+        read_chunked(reader)?
+    } else {
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        body
+    };
+
+    Ok(Some(HttpRequest {
+        method,
+        path,
+        headers,
+        body,
+    }))
+}
+
+/// Writes a response with the given headers and a `Content-Length`.
+pub fn write_response(
+    stream: &mut impl Write,
+    status: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> io::Result<()> {
+    let mut response = format!("HTTP/1.1 {status}\r\n");
+    for (name, value) in headers {
+        response.push_str(&format!("{name}: {value}\r\n"));
+    }
+    response.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+
+    stream.write_all(response.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+fn write_json(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> io::Result<()> {
+    let mut headers = headers.to_vec();
+    headers.push(("Content-Type", "application/json".to_owned()));
+    write_response(stream, status, &headers, body)
+}
+
+impl State {
+    fn is_shut_down(&self) -> bool {
+        *self.shutdown.lock().unwrap()
+    }
+
+    fn finish(&self, id: &str, outcome: Outcome) {
+        if let Some(sender) = self.pending.lock().unwrap().remove(id) {
+            let _ = sender.send(outcome);
+        }
+    }
+
+    /// Fails every invocation waiting for an answer, when the runtime can't initialize.
+    fn fail_all(&self, error: &[u8]) {
+        for (_, sender) in self.pending.lock().unwrap().drain() {
+            let _ = sender.send(Outcome::Error(error.to_vec()));
+        }
+    }
+
+    /// Handles one request, returns `false` when the connection should be closed.
+    fn handle(&self, stream: &mut TcpStream, request: HttpRequest) -> io::Result<bool> {
+        let path = request.path.as_str();
+
+        match (request.method.as_str(), path) {
+            ("GET", "/2018-06-01/runtime/invocation/next") => {
+                // blocks until there is something to run, or the emulator is dropped
+                let Ok(invocation) = self.invocations.lock().unwrap().recv() else {
+                    return Ok(false);
+                };
+
+                let deadline = now_ms() + DEADLINE.as_millis();
+                write_json(
+                    stream,
+                    "200 OK",
+                    &[
+                        ("Lambda-Runtime-Aws-Request-Id", invocation.id.clone()),
+                        ("Lambda-Runtime-Deadline-Ms", deadline.to_string()),
+                        (
+                            "Lambda-Runtime-Invoked-Function-Arn",
+                            format!(
+                                "arn:aws:lambda:us-east-1:000000000000:function:{FUNCTION_NAME}"
+                            ),
+                        ),
+                        (
+                            "Lambda-Runtime-Trace-Id",
+                            format!("Root=1-00000000-{};Sampled=0", invocation.id),
+                        ),
+                    ],
+                    &invocation.event,
+                )?;
+            }
+            ("POST", "/2018-06-01/runtime/init/error") => {
+                self.fail_all(&request.body);
+                write_json(stream, "202 Accepted", &[], b"{}")?;
+            }
+            ("POST", _) if path.starts_with("/2018-06-01/runtime/invocation/") => {
+                let rest = &path["/2018-06-01/runtime/invocation/".len()..];
+                let outcome = match rest.split_once('/') {
+                    Some((id, "response")) => Some((id, Outcome::Response(request.body))),
+                    Some((id, "error")) => Some((id, Outcome::Error(request.body))),
+                    _ => None,
+                };
+
+                match outcome {
+                    Some((id, outcome)) => {
+                        self.finish(id, outcome);
+                        write_json(stream, "202 Accepted", &[], b"{}")?;
+                    }
+                    None => write_json(stream, "404 Not Found", &[], b"{}")?,
+                }
+            }
+            ("POST", "/2020-01-01/extension/register") => {
+                let body = json!({
+                    "functionName": FUNCTION_NAME,
+                    "functionVersion": "$LATEST",
+                    "handler": "index.handler",
+                });
+                write_json(
+                    stream,
+                    "200 OK",
+                    &[("Lambda-Extension-Identifier", format!("{:x}", now_ms()))],
+                    body.to_string().as_bytes(),
+                )?;
+            }
+            ("GET", "/2020-01-01/extension/event/next") => {
+                // extensions only hear back on shutdown, and only if they subscribed to it
+                let mut shutdown = self.shutdown.lock().unwrap();
+                while !*shutdown {
+                    shutdown = self.shutdown_changed.wait(shutdown).unwrap();
+                }
+                return Ok(false);
+            }
+            _ => write_json(stream, "404 Not Found", &[], b"{}")?,
+        }
+
+        Ok(true)
+    }
+
+    fn serve_connection(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        while let Some(request) = read_request(&mut reader)? {
+            if !self.handle(&mut stream, request)? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Emulator {
+    /// Starts listening on a free local port.
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+
+        let (sender, receiver) = mpsc::channel();
+        let state = Arc::new(State {
+            invocations: Mutex::new(receiver),
+            pending: Mutex::new(HashMap::new()),
+            shutdown: Mutex::new(false),
+            shutdown_changed: Condvar::new(),
+        });
+
+        let accept_state = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accept_state.is_shut_down() {
+                    break;
+                }
+
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let state = accept_state.clone();
+                thread::spawn(move || {
+                    let _ = state.serve_connection(stream);
+                });
+            }
+        });
+
+        Ok(Emulator {
+            address,
+            state,
+            invocations: Some(sender),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    /// The environment a Lambda runtime needs to talk to this emulator.
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("AWS_LAMBDA_RUNTIME_API", self.address.to_string()),
+            ("AWS_LAMBDA_FUNCTION_NAME", FUNCTION_NAME.to_owned()),
+            ("AWS_LAMBDA_FUNCTION_VERSION", "$LATEST".to_owned()),
+            ("AWS_LAMBDA_FUNCTION_MEMORY_SIZE", "1024".to_owned()),
+            (
+                "AWS_LAMBDA_LOG_GROUP_NAME",
+                format!("/aws/lambda/{FUNCTION_NAME}"),
+            ),
+            ("AWS_LAMBDA_LOG_STREAM_NAME", "local".to_owned()),
+        ]
+    }
+
+    /// Queues an event for the runtime. The receiver gets the outcome once the runtime answers.
+    pub fn invoke(&self, event: Vec<u8>) -> Receiver<Outcome> {
+        let id = format!(
+            "00000000-0000-4000-8000-{:012x}",
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        self.state
+            .pending
+            .lock()
+            .unwrap()
+            .insert(id.clone(), sender);
+
+        if let Some(invocations) = &self.invocations {
+            let _ = invocations.send(Invocation { id, event });
+        }
+
+        receiver
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        *self.state.shutdown.lock().unwrap() = true;
+        self.state.shutdown_changed.notify_all();
+
+        // unblocks the runtime waiting for its next invocation
+        self.invocations.take();
+
+        // wakes up the accepting thread so it can see the shutdown
+        let _ = TcpStream::connect(self.address);
+    }
+}
+
+/// The decoded key and value of every parameter of a query string, `+` decoded to a space like
+/// Lambda does.
+fn query_pairs(query: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    form_urlencoded::parse(query.as_bytes())
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+}
+
+/// Adds a value to a map of comma separated values, the way Lambda merges repeated headers and
+/// query parameters.
+fn insert_joined(map: &mut Map<String, Value>, key: String, value: String) {
+    match map.get_mut(&key) {
+        Some(Value::String(existing)) => {
+            existing.push(',');
+            existing.push_str(&value);
+        }
+        _ => {
+            map.insert(key, Value::String(value));
+        }
+    }
+}
+
+/// Formats a Unix timestamp the way Lambda does in `requestContext.time`, like
+/// `12/Mar/2024:19:03:58 +0000`.
+fn format_request_time(ms: u128) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let seconds = (ms / 1000) as i64;
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+    // days since the epoch to a civil date, from Howard Hinnant's `civil_from_days`
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{day:02}/{}/{year}:{:02}:{:02}:{:02} +0000",
+        MONTHS[(month - 1) as usize],
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// A Function URL event, the payload Lambda sends for HTTP requests. Cookies go to `cookies`
+/// instead of the headers, repeated headers and query parameters are joined with commas.
+pub fn function_url_event(request: &HttpRequest, source_ip: &str) -> Vec<u8> {
+    let (path, query) = request
+        .path
+        .split_once('?')
+        .unwrap_or((request.path.as_str(), ""));
+
+    let mut headers = Map::new();
+    let mut cookies = Vec::new();
+    for (name, value) in &request.headers {
+        let name = name.to_ascii_lowercase();
+        if name == "cookie" {
+            cookies.extend(
+                value
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|cookie| !cookie.is_empty())
+                    .map(str::to_owned),
+            );
+        } else {
+            insert_joined(&mut headers, name, value.clone());
+        }
+    }
+
+    let mut parameters = Map::new();
+    for (key, value) in query_pairs(query) {
+        insert_joined(&mut parameters, key, value);
+    }
+
+    let domain_name = headers
+        .get("host")
+        .and_then(Value::as_str)
+        .unwrap_or("localhost")
+        .to_owned();
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_owned();
+
+    let now = now_ms();
+    let mut event = json!({
+        "version": "2.0",
+        "routeKey": "$default",
+        "rawPath": path,
+        "rawQueryString": query,
+        "headers": headers,
+        "requestContext": {
+            "accountId": "anonymous",
+            "apiId": FUNCTION_NAME,
+            "domainName": domain_name,
+            "domainPrefix": domain_name.split('.').next().unwrap_or_default(),
+            "http": {
+                "method": request.method,
+                "path": path,
+                "protocol": "HTTP/1.1",
+                "sourceIp": source_ip,
+                "userAgent": user_agent,
+            },
+            "requestId": format!("{now:x}"),
+            "routeKey": "$default",
+            "stage": "$default",
+            "time": format_request_time(now),
+            "timeEpoch": now as u64,
+        },
+        "isBase64Encoded": false,
+    });
+
+    // Lambda leaves out what the request doesn't have
+    if !cookies.is_empty() {
+        event["cookies"] = json!(cookies);
+    }
+    if !parameters.is_empty() {
+        event["queryStringParameters"] = Value::Object(parameters);
+    }
+    add_body(&mut event, &request.body);
+
+    event.to_string().into_bytes()
+}
+
+/// Adds a value to a map of lists, the way API Gateway keeps every value of repeated headers and
+/// query parameters in `multiValueHeaders` and `multiValueQueryStringParameters`.
+fn push_multi(map: &mut Map<String, Value>, key: String, value: String) {
+    match map.get_mut(&key) {
+        Some(Value::Array(values)) => values.push(Value::String(value)),
+        _ => {
+            map.insert(key, json!([value]));
+        }
+    }
+}
+
+/// An API Gateway REST API event, the payload of a proxy integration (version 1.0). The single
+/// value maps keep the last value of repeated headers and query parameters, the multi value ones
+/// all of them.
+pub fn api_gateway_event(request: &HttpRequest, source_ip: &str) -> Vec<u8> {
+    let (path, query) = request
+        .path
+        .split_once('?')
+        .unwrap_or((request.path.as_str(), ""));
+
+    let mut headers = Map::new();
+    let mut multi_value_headers = Map::new();
+    for (name, value) in &request.headers {
+        let name = name.to_ascii_lowercase();
+        headers.insert(name.clone(), json!(value));
+        push_multi(&mut multi_value_headers, name, value.clone());
+    }
+
+    let mut parameters = Map::new();
+    let mut multi_value_parameters = Map::new();
+    for (key, value) in query_pairs(query) {
+        parameters.insert(key.clone(), json!(value));
+        push_multi(&mut multi_value_parameters, key, value);
+    }
+
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_owned();
+
+    let now = now_ms();
+    let mut event = json!({
+        "resource": "/{proxy+}",
+        "path": path,
+        "httpMethod": request.method,
+        "headers": headers,
+        "multiValueHeaders": multi_value_headers,
+        "queryStringParameters": null,
+        "multiValueQueryStringParameters": null,
+        "pathParameters": { "proxy": path.trim_start_matches('/') },
+        "stageVariables": null,
+        "requestContext": {
+            "accountId": "anonymous",
+            "apiId": FUNCTION_NAME,
+            "resourceId": "proxy",
+            "resourcePath": "/{proxy+}",
+            "httpMethod": request.method,
+            "path": path,
+            "protocol": "HTTP/1.1",
+            // lambda_http adds any other stage in front of the path, like a stage in the URL
+            "stage": "$default",
+            "requestId": format!("{now:x}"),
+            "requestTime": format_request_time(now),
+            "requestTimeEpoch": now as u64,
+            "identity": {
+                "sourceIp": source_ip,
+                "userAgent": user_agent,
+            },
+        },
+        "body": null,
+        "isBase64Encoded": false,
+    });
+
+    // unlike Function URLs, API Gateway sends null for what the request doesn't have
+    if !parameters.is_empty() {
+        event["queryStringParameters"] = Value::Object(parameters);
+        event["multiValueQueryStringParameters"] = Value::Object(multi_value_parameters);
+    }
+    add_body(&mut event, &request.body);
+
+    event.to_string().into_bytes()
+}
+
+/// Sets the body of an event, base64 encoded when it isn't text.
+fn add_body(event: &mut Value, body: &[u8]) {
+    if body.is_empty() {
+        return;
+    }
+
+    match std::str::from_utf8(body) {
+        Ok(text) => event["body"] = json!(text),
+        Err(_) => {
+            event["body"] = json!(general_purpose::STANDARD.encode(body));
+            event["isBase64Encoded"] = json!(true);
+        }
+    }
+}
+
+/// Decodes the answer to a Function URL or API Gateway event. Like Lambda, anything that isn't an
+/// object with a `statusCode` is sent back as a JSON body.
+pub fn read_function_url_response(payload: &[u8]) -> HttpResponse {
+    let value = serde_json::from_slice::<Value>(payload).unwrap_or(Value::Null);
+    let status = match &value["statusCode"] {
+        Value::Number(status) => status.as_u64(),
+        Value::String(status) => status.parse().ok(),
+        _ => None,
+    };
+
+    let Some(status) = status.and_then(|status| u16::try_from(status).ok()) else {
+        return HttpResponse {
+            status: 200,
+            headers: vec![("Content-Type".to_owned(), "application/json".to_owned())],
+            body: payload.to_vec(),
+        };
+    };
+
+    let multi_value_headers = value["multiValueHeaders"].as_object();
+
+    let mut headers = Vec::new();
+    if let Some(map) = value["headers"].as_object() {
+        for (name, value) in map {
+            // API Gateway responses often have the same header in both maps
+            if multi_value_headers.is_some_and(|multi| multi.contains_key(name)) {
+                continue;
+            }
+
+            if let Some(value) = value.as_str() {
+                headers.push((name.clone(), value.to_owned()));
+            }
+        }
+    }
+    if let Some(map) = multi_value_headers {
+        for (name, values) in map {
+            for value in values.as_array().into_iter().flatten() {
+                if let Some(value) = value.as_str() {
+                    headers.push((name.clone(), value.to_owned()));
+                }
+            }
+        }
+    }
+    for cookie in value["cookies"].as_array().into_iter().flatten() {
+        if let Some(cookie) = cookie.as_str() {
+            headers.push(("Set-Cookie".to_owned(), cookie.to_owned()));
+        }
+    }
+
+    let body = value["body"].as_str().unwrap_or_default();
+    let body = if value["isBase64Encoded"].as_bool().unwrap_or(false) {
+        general_purpose::STANDARD
+            .decode(body)
+            .unwrap_or_else(|_| body.as_bytes().to_vec())
+    } else {
+        body.as_bytes().to_vec()
+    };
+
+    HttpResponse {
+        status,
+        headers,
+        body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(path: &str, headers: &[(&str, &str)]) -> HttpRequest {
+        HttpRequest {
+            method: "GET".to_owned(),
+            path: path.to_owned(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: vec![],
+        }
+    }
+
+    #[test]
+    fn content_length_bodies() {
+        let mut reader: &[u8] =
+            b"POST /a?b=1 HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\nhelloGET / HTTP/1.1\r\n\r\n";
+
+        let request = read_request(&mut reader).unwrap().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/a?b=1");
+        assert_eq!(request.body, b"hello");
+
+        let next = read_request(&mut reader).unwrap().unwrap();
+        assert_eq!(next.method, "GET");
+        assert!(next.body.is_empty());
+        assert!(read_request(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn chunked_bodies() {
+        let mut reader: &[u8] = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5;ext=1\r\nhello\r\n7\r\n, world\r\n0\r\nTrailer: x\r\n\r\n";
+
+        let request = read_request(&mut reader).unwrap().unwrap();
+        assert_eq!(request.body, b"hello, world");
+        assert!(read_request(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn query_decoding() {
+        let pairs = query_pairs("a+b=%20c%2Bd&e=%e2%82%ac&f=100%&g=%zz%4&&h")
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, ["a b= c+d", "e=€", "f=100%", "g=%zz%4", "h="]);
+    }
+
+    #[test]
+    fn repeated_query_keys_and_headers() {
+        let event = function_url_event(
+            &request(
+                "/items?tag=a&tag=b+c&empty&q=%3D",
+                &[
+                    ("Accept", "text/html"),
+                    ("accept", "*/*"),
+                    ("Cookie", "a=1; b=2"),
+                ],
+            ),
+            "127.0.0.1",
+        );
+        let event = serde_json::from_slice::<Value>(&event).unwrap();
+
+        assert_eq!(event["rawPath"], "/items");
+        assert_eq!(event["rawQueryString"], "tag=a&tag=b+c&empty&q=%3D");
+        assert_eq!(
+            event["queryStringParameters"],
+            json!({ "tag": "a,b c", "empty": "", "q": "=" })
+        );
+        assert_eq!(event["headers"]["accept"], "text/html,*/*");
+        assert_eq!(event["cookies"], json!(["a=1", "b=2"]));
+        assert!(event.get("body").is_none());
+    }
+
+    #[test]
+    fn api_gateway_events() {
+        let mut post = request(
+            "/items/1?tag=a&tag=b+c",
+            &[
+                ("Accept", "text/html"),
+                ("accept", "*/*"),
+                ("Cookie", "a=1"),
+            ],
+        );
+        post.method = "POST".to_owned();
+        post.body = vec![0xff, 0x00];
+
+        let event = api_gateway_event(&post, "10.0.0.1");
+        let event = serde_json::from_slice::<Value>(&event).unwrap();
+
+        assert_eq!(event["path"], "/items/1");
+        assert_eq!(event["httpMethod"], "POST");
+        assert_eq!(event["pathParameters"]["proxy"], "items/1");
+        assert_eq!(event["headers"]["accept"], "*/*");
+        assert_eq!(
+            event["multiValueHeaders"]["accept"],
+            json!(["text/html", "*/*"])
+        );
+        assert_eq!(event["headers"]["cookie"], "a=1");
+        assert_eq!(event["queryStringParameters"], json!({ "tag": "b c" }));
+        assert_eq!(
+            event["multiValueQueryStringParameters"],
+            json!({ "tag": ["a", "b c"] })
+        );
+        assert_eq!(event["requestContext"]["stage"], "$default");
+        assert_eq!(event["requestContext"]["identity"]["sourceIp"], "10.0.0.1");
+        assert_eq!(event["body"], "/wA=");
+        assert_eq!(event["isBase64Encoded"], true);
+
+        let event = api_gateway_event(&request("/", &[]), "127.0.0.1");
+        let event = serde_json::from_slice::<Value>(&event).unwrap();
+        assert_eq!(event["queryStringParameters"], Value::Null);
+        assert_eq!(event["body"], Value::Null);
+    }
+
+    #[test]
+    fn request_time() {
+        // 2024-03-12T19:03:58.123Z
+        assert_eq!(
+            format_request_time(1_710_270_238_123),
+            "12/Mar/2024:19:03:58 +0000"
+        );
+        assert_eq!(format_request_time(0), "01/Jan/1970:00:00:00 +0000");
+        // 2000-02-29T23:59:59Z
+        assert_eq!(
+            format_request_time(951_868_799_000),
+            "29/Feb/2000:23:59:59 +0000"
+        );
+    }
+
+    #[test]
+    fn function_url_responses() {
+        let response = read_function_url_response(
+            br#"{
+                "statusCode": 201,
+                "headers": { "content-type": "text/plain" },
+                "multiValueHeaders": { "vary": ["a", "b"] },
+                "cookies": ["a=1"],
+                "body": "aGk=",
+                "isBase64Encoded": true
+            }"#,
+        );
+        assert_eq!(response.status, 201);
+        assert_eq!(
+            response.headers,
+            [
+                ("content-type".to_owned(), "text/plain".to_owned()),
+                ("vary".to_owned(), "a".to_owned()),
+                ("vary".to_owned(), "b".to_owned()),
+                ("Set-Cookie".to_owned(), "a=1".to_owned()),
+            ]
+        );
+        assert_eq!(response.body, b"hi");
+
+        let response = read_function_url_response(br#"{"statusCode":"404"}"#);
+        assert_eq!(response.status, 404);
+        assert!(response.body.is_empty());
+
+        // the API Gateway format, with headers in both maps
+        let response = read_function_url_response(
+            br#"{
+                "statusCode": 200,
+                "headers": { "vary": "b", "x-a": "1" },
+                "multiValueHeaders": { "vary": ["a", "b"] },
+                "body": "ok"
+            }"#,
+        );
+        assert_eq!(
+            response.headers,
+            [
+                ("x-a".to_owned(), "1".to_owned()),
+                ("vary".to_owned(), "a".to_owned()),
+                ("vary".to_owned(), "b".to_owned()),
+            ]
+        );
+        assert_eq!(response.body, b"ok");
+
+        // anything else is the body
+        let response = read_function_url_response(br#"{"hello":"world"}"#);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, br#"{"hello":"world"}"#);
+    }
+}
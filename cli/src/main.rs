@@ -4,9 +4,17 @@ use colored::Colorize;
 use cmd::Command;
 use options::GlobalOptions;
 
+mod archive;
+mod artifacts;
+mod build_manifest;
+mod cargo_config;
 mod cmd;
+mod emulator;
+mod manifest;
 mod metadata;
 mod options;
+mod sdk;
+mod services;
 
 #[derive(Debug, Parser)]
 #[clap(name = "genezio-rs", version)]
@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt::Display, fs, path::Path};
+
+const REGIONS: &[&str] = &[
+    "us-east-1",
+    "us-east-2",
+    "us-west-1",
+    "us-west-2",
+    "ca-central-1",
+    "sa-east-1",
+    "eu-central-1",
+    "eu-west-1",
+    "eu-west-2",
+    "eu-west-3",
+    "eu-north-1",
+    "ap-south-1",
+    "ap-northeast-1",
+    "ap-northeast-2",
+    "ap-northeast-3",
+    "ap-southeast-1",
+    "ap-southeast-2",
+];
+
+const CLOUD_PROVIDERS: &[&str] = &["genezio", "aws", "selfHostedAws", "cluster"];
+
+const LANGUAGES: &[&str] = &["rust", "js", "ts"];
+
+/// The `genezio.yaml` of a project. Keys this CLI doesn't know about are kept as they are.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenezioManifest {
+    pub name: String,
+    pub region: String,
+    pub language: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloud_provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub classes: Vec<ClassEntry>,
+    #[serde(flatten)]
+    pub other: serde_yaml::Mapping,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClassEntry {
+    pub path: String,
+    #[serde(flatten)]
+    pub other: serde_yaml::Mapping,
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|ch| ch.is_ascii_alphabetic())
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+}
+
+fn one_of(key: &str, value: &str, allowed: &[&str]) -> Result<(), (String, String)> {
+    if allowed.contains(&value) {
+        return Ok(());
+    }
+
+    Err((
+        key.to_owned(),
+        format!(
+            "unknown value `{value}`, expected one of: {}",
+            allowed.join(", ")
+        ),
+    ))
+}
+
+/// Finds the line of a top level key, for pointing errors at it.
+fn key_line(text: &str, key: &str) -> Option<usize> {
+    text.lines()
+        .position(|line| {
+            line.strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with(':'))
+        })
+        .map(|index| index + 1)
+}
+
+impl GenezioManifest {
+    pub fn read(path: &Path) -> Result<Self, ManifestError> {
+        if !path.exists() {
+            return Err(ManifestError::NotFound);
+        }
+
+        let text = fs::read_to_string(path).map_err(|e| ManifestError::Read(e.to_string()))?;
+        let manifest: Self =
+            serde_yaml::from_str(&text).map_err(|e| ManifestError::Parse(e.to_string()))?;
+
+        manifest
+            .validate()
+            .map_err(|(key, message)| ManifestError::Invalid {
+                line: key_line(&text, &key),
+                key,
+                message,
+            })?;
+
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> Result<(), (String, String)> {
+        if !is_valid_name(&self.name) {
+            return Err((
+                "name".to_owned(),
+                format!(
+                    "`{}` must start with a letter and only contain letters, digits and dashes",
+                    self.name
+                ),
+            ));
+        }
+
+        one_of("region", &self.region, REGIONS)?;
+        one_of("language", &self.language, LANGUAGES)?;
+
+        if let Some(cloud_provider) = &self.cloud_provider {
+            one_of("cloudProvider", cloud_provider, CLOUD_PROVIDERS)?;
+        }
+
+        for (index, class) in self.classes.iter().enumerate() {
+            if class.path.trim().is_empty() {
+                return Err((
+                    format!("classes[{index}].path"),
+                    "must not be empty".to_owned(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds an entry for every generated class file, keeping the settings of entries the
+    /// manifest already has for the same file. Entries for files the build didn't generate are an
+    /// error, genezio would fail to deploy them.
+    pub fn merge_classes(&mut self, files: &[String]) -> Result<(), ManifestError> {
+        for (index, class) in self.classes.iter().enumerate() {
+            if !files
+                .iter()
+                .any(|file| class.path.trim_start_matches("./") == file)
+            {
+                return Err(ManifestError::Invalid {
+                    key: format!("classes[{index}].path"),
+                    line: None,
+                    message: format!(
+                        "`{}` is not generated by this build, which writes {}",
+                        class.path,
+                        files
+                            .iter()
+                            .map(|file| format!("`./{file}`"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                });
+            }
+        }
+
+        for file in files {
+            let exists = self
+                .classes
+                .iter()
+                .any(|class| class.path.trim_start_matches("./") == file);
+
+            if !exists {
+                self.classes.push(ClassEntry {
+                    path: format!("./{file}"),
+                    other: serde_yaml::Mapping::new(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ManifestError> {
+        let text = serde_yaml::to_string(self).map_err(|e| ManifestError::Write(e.to_string()))?;
+        fs::write(path, text).map_err(|e| ManifestError::Write(e.to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    NotFound,
+    Read(String),
+    Parse(String),
+    Invalid {
+        key: String,
+        line: Option<usize>,
+        message: String,
+    },
+    Write(String),
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ManifestError: {}",
+            match self {
+                ManifestError::NotFound => "genezio.yaml not found in workspace root".to_string(),
+                ManifestError::Read(text) => format!("failed to read genezio.yaml: {text}"),
+                ManifestError::Parse(text) => format!("invalid genezio.yaml: {text}"),
+                ManifestError::Invalid {
+                    key,
+                    line: Some(line),
+                    message,
+                } => format!("genezio.yaml:{line}: invalid `{key}`: {message}"),
+                ManifestError::Invalid {
+                    key,
+                    line: None,
+                    message,
+                } => format!("genezio.yaml: invalid `{key}`: {message}"),
+                ManifestError::Write(text) => format!("failed to write genezio.yaml: {text}"),
+            }
+        )
+    }
+}
+
+impl Error for ManifestError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(classes: &str) -> GenezioManifest {
+        serde_yaml::from_str(&format!(
+            "name: app\nregion: us-east-1\nlanguage: js\nclasses:\n{classes}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn merge_classes_keeps_settings_of_generated_files() {
+        let mut manifest = manifest("  - path: ./api.js\n    type: jsonrpc\n");
+        manifest
+            .merge_classes(&["api.js".to_owned(), "web.js".to_owned()])
+            .unwrap();
+
+        let paths = manifest
+            .classes
+            .iter()
+            .map(|class| class.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["./api.js", "./web.js"]);
+        assert_eq!(
+            manifest.classes[0].other.get("type"),
+            Some(&serde_yaml::Value::from("jsonrpc"))
+        );
+        assert!(manifest.classes[1].other.is_empty());
+    }
+
+    #[test]
+    fn merge_classes_rejects_files_not_generated() {
+        let mut manifest = manifest("  - path: ./index.js\n");
+        let err = manifest
+            .merge_classes(&["api.js".to_owned()])
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            err,
+            "ManifestError: genezio.yaml: invalid `classes[0].path`: `./index.js` is not \
+             generated by this build, which writes `./api.js`"
+        );
+    }
+
+    #[test]
+    fn validate_accepts_the_languages_genezio_knows() {
+        for language in ["rust", "js", "ts"] {
+            let manifest: GenezioManifest = serde_yaml::from_str(&format!(
+                "name: app\nregion: us-east-1\nlanguage: {language}\n"
+            ))
+            .unwrap();
+            assert_eq!(manifest.validate(), Ok(()), "{language}");
+        }
+
+        let manifest: GenezioManifest =
+            serde_yaml::from_str("name: app\nregion: us-east-1\nlanguage: python\n").unwrap();
+        assert_eq!(manifest.validate().unwrap_err().0, "language");
+    }
+}
@@ -1,19 +1,261 @@
 use serde::Deserialize;
+use serde_json::Value;
 use std::{path::PathBuf, process::Command};
 
 #[derive(Debug, Deserialize)]
 pub struct CargoMetadata {
     pub target_directory: PathBuf,
     pub workspace_root: PathBuf,
+    #[serde(default)]
+    pub packages: Vec<CargoPackage>,
+    /// The `[workspace.metadata]` table.
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CargoPackage {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    pub targets: Vec<CargoTarget>,
+    /// The `[package.metadata]` table.
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CargoTarget {
+    pub name: String,
+    pub kind: Vec<String>,
+}
+
+impl CargoTarget {
+    pub fn is_bin(&self) -> bool {
+        self.kind.iter().any(|kind| kind == "bin")
+    }
+}
+
+/// The `genezio` table under `[package.metadata]` or `[workspace.metadata]`.
+#[derive(Debug, Default, Deserialize)]
+struct GenezioMetadata {
+    bins: Option<Vec<String>>,
+}
+
+/// A binary target that gets packaged as its own genezio class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenezioBin {
+    pub package: String,
+    pub name: String,
+}
+
+fn genezio_metadata(metadata: &Option<Value>) -> Result<Option<GenezioMetadata>, String> {
+    let Some(genezio) = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("genezio"))
+    else {
+        return Ok(None);
+    };
+
+    serde_json::from_value(genezio.clone())
+        .map(Some)
+        .map_err(|e| format!("invalid genezio metadata: {e}"))
 }
 
 impl CargoMetadata {
     pub fn get_genezio_out_dir(&self) -> PathBuf {
         self.target_directory.join("genezio/out")
     }
+
+    pub fn get_genezio_sdk_dir(&self) -> PathBuf {
+        self.target_directory.join("genezio/sdk")
+    }
+
+    pub fn get_genezio_host_dir(&self) -> PathBuf {
+        self.target_directory.join("genezio/host")
+    }
+
+    pub fn get_genezio_symbols_dir(&self) -> PathBuf {
+        self.target_directory.join("genezio/symbols")
+    }
+
+    pub fn get_genezio_package_path(&self) -> PathBuf {
+        self.target_directory.join("genezio/package.zip")
+    }
+
+    pub fn get_genezio_deploy_dir(&self) -> PathBuf {
+        self.target_directory.join("genezio/deploy")
+    }
+
+    fn find_bin(&self, name: &str) -> Result<GenezioBin, String> {
+        let mut packages = self.packages.iter().filter(|package| {
+            package
+                .targets
+                .iter()
+                .any(|target| target.is_bin() && target.name == name)
+        });
+
+        let package = packages
+            .next()
+            .ok_or_else(|| format!("no binary named `{name}` in the workspace"))?;
+
+        if let Some(other) = packages.next() {
+            return Err(format!(
+                "packages `{}` and `{}` both have a binary named `{name}`, list it in the \
+                 `[package.metadata.genezio]` of the one to deploy",
+                package.name, other.name
+            ));
+        }
+
+        Ok(GenezioBin {
+            package: package.name.clone(),
+            name: name.to_owned(),
+        })
+    }
+
+    /// Collects the binaries marked as genezio services, either with a `[package.metadata.genezio]`
+    /// table (optionally listing `bins`) or with `bins` in `[workspace.metadata.genezio]`.
+    pub fn get_genezio_bins(&self) -> Result<Vec<GenezioBin>, String> {
+        let mut bins = Vec::new();
+
+        if let Some(genezio) = genezio_metadata(&self.metadata)? {
+            for name in genezio.bins.unwrap_or_default() {
+                bins.push(self.find_bin(&name)?);
+            }
+        }
+
+        for package in &self.packages {
+            let Some(genezio) = genezio_metadata(&package.metadata)? else {
+                continue;
+            };
+
+            let targets = package.targets.iter().filter(|target| target.is_bin());
+            match genezio.bins {
+                Some(names) => {
+                    for name in names {
+                        if !targets.clone().any(|target| target.name == name) {
+                            return Err(format!(
+                                "package `{}` has no binary named `{name}`",
+                                package.name
+                            ));
+                        }
+
+                        bins.push(GenezioBin {
+                            package: package.name.clone(),
+                            name,
+                        });
+                    }
+                }
+                None => bins.extend(targets.map(|target| GenezioBin {
+                    package: package.name.clone(),
+                    name: target.name.clone(),
+                })),
+            }
+        }
+
+        // by name first, so the output keeps its order and same-named bins end up side by side
+        bins.sort_by(|a, b| (&a.name, &a.package).cmp(&(&b.name, &b.package)));
+        bins.dedup();
+
+        // the class file and the binary in target/genezio/out are named after the binary
+        if let Some(pair) = bins.windows(2).find(|pair| pair[0].name == pair[1].name) {
+            return Err(format!(
+                "packages `{}` and `{}` both have a binary named `{}`, rename one of them to \
+                 deploy both",
+                pair[0].package, pair[1].package, pair[0].name
+            ));
+        }
+
+        Ok(bins)
+    }
 }
 
 pub fn get_cargo_metadata() -> Result<CargoMetadata, Box<dyn std::error::Error>> {
-    let text = Command::new("cargo").arg("metadata").output()?.stdout;
+    let text = Command::new("cargo")
+        .arg("metadata")
+        .arg("--no-deps")
+        .arg("--format-version=1")
+        .output()?
+        .stdout;
     Ok(serde_json::from_slice(&text)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata(workspace: Value, packages: Value) -> CargoMetadata {
+        serde_json::from_value(json!({
+            "target_directory": "/target",
+            "workspace_root": "/",
+            "metadata": workspace,
+            "packages": packages,
+        }))
+        .unwrap()
+    }
+
+    fn package(name: &str, bins: &[&str], genezio: Value) -> Value {
+        json!({
+            "name": name,
+            "manifest_path": format!("/{name}/Cargo.toml"),
+            "targets": bins
+                .iter()
+                .map(|bin| json!({ "name": bin, "kind": ["bin"] }))
+                .collect::<Vec<_>>(),
+            "metadata": genezio,
+        })
+    }
+
+    #[test]
+    fn bins_are_sorted_and_deduplicated() {
+        let metadata = metadata(
+            json!({ "genezio": { "bins": ["web"] } }),
+            json!([
+                package("web", &["web", "tool"], json!({ "genezio": {} })),
+                package("api", &["api"], json!({ "genezio": {} })),
+            ]),
+        );
+
+        let names = metadata
+            .get_genezio_bins()
+            .unwrap()
+            .into_iter()
+            .map(|bin| format!("{}/{}", bin.package, bin.name))
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["api/api", "web/tool", "web/web"]);
+    }
+
+    #[test]
+    fn same_named_bins_are_rejected() {
+        let metadata = metadata(
+            Value::Null,
+            json!([
+                package("a", &["api"], json!({ "genezio": {} })),
+                package("b", &["api"], json!({ "genezio": {} })),
+            ]),
+        );
+
+        let err = metadata.get_genezio_bins().unwrap_err();
+        assert!(
+            err.contains("`a` and `b` both have a binary named `api`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn ambiguous_workspace_bins_are_rejected() {
+        let metadata = metadata(
+            json!({ "genezio": { "bins": ["api"] } }),
+            json!([
+                package("a", &["api"], Value::Null),
+                package("b", &["api"], Value::Null),
+            ]),
+        );
+
+        let err = metadata.get_genezio_bins().unwrap_err();
+        assert!(
+            err.contains("`a` and `b` both have a binary named `api`"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::{fs, io, path::Path};
+
+pub mod rust;
+pub mod typescript;
+
+/// Turns a `PascalCase` service name into a `kebab-case` file name.
+fn file_name(name: &str) -> String {
+    let mut out = String::new();
+    for (i, ch) in name.char_indices() {
+        if i > 0 && ch.is_uppercase() {
+            out.push('-');
+        }
+        out.push(ch.to_ascii_lowercase());
+    }
+    out
+}
+
+fn write_file(dir: &Path, name: &str, contents: &str) -> io::Result<()> {
+    fs::write(dir.join(name), contents.trim_start())
+}
+
+/// A service and a few types in the format `#[genezio::service]` embeds them, for the generator tests.
+#[cfg(test)]
+pub(crate) fn example() -> crate::services::Metadata {
+    use serde_json::json;
+
+    let string = json!({ "name": "String" });
+    let services = json!([{
+        "name": "Users",
+        "methods": [
+            {
+                "name": "get",
+                "params": [{ "name": "id", "type": { "name": "u64" } }],
+                "returns": { "name": "Option", "args": [{ "name": "User" }] },
+            },
+            {
+                "name": "list",
+                "params": [],
+                "returns": { "name": "Page", "args": [{ "name": "User" }] },
+            },
+            { "name": "ping", "params": [] },
+        ],
+    }]);
+    let types = json!([
+        {
+            "name": "User",
+            "generics": [],
+            "shape": { "kind": "struct", "fields": [
+                { "name": "name", "type": string, "optional": false, "flatten": false },
+                {
+                    "name": "email",
+                    "type": { "name": "Option", "args": [string] },
+                    "optional": true,
+                    "flatten": false,
+                },
+                {
+                    "name": "tags",
+                    "type": { "name": "Vec", "args": [string] },
+                    "optional": false,
+                    "flatten": false,
+                },
+                {
+                    "name": "scores",
+                    "type": { "name": "HashMap", "args": [string, { "name": "u32" }] },
+                    "optional": false,
+                    "flatten": false,
+                },
+                {
+                    "name": "position",
+                    "type": { "tuple": [{ "name": "f64" }, { "name": "f64" }] },
+                    "optional": false,
+                    "flatten": false,
+                },
+            ] },
+        },
+        {
+            "name": "Page",
+            "generics": ["T"],
+            "shape": { "kind": "struct", "fields": [
+                {
+                    "name": "items",
+                    "type": { "name": "Vec", "args": [{ "name": "T" }] },
+                    "optional": false,
+                    "flatten": false,
+                },
+            ] },
+        },
+        {
+            "name": "Event",
+            "generics": [],
+            "shape": { "kind": "enum", "tag": "type", "content": null, "untagged": false, "variants": [
+                { "name": "Created", "shape": { "kind": "struct", "fields": [
+                    { "name": "id", "type": { "name": "u64" }, "optional": false, "flatten": false },
+                ] } },
+                { "name": "Deleted", "shape": { "kind": "unit" } },
+            ] },
+        },
+        {
+            "name": "Status",
+            "generics": [],
+            "shape": { "kind": "enum", "tag": null, "content": null, "untagged": false, "variants": [
+                { "name": "Active", "shape": { "kind": "unit" } },
+                { "name": "Banned", "shape": { "kind": "tuple", "items": [string] } },
+            ] },
+        },
+    ]);
+
+    crate::services::Metadata {
+        services: serde_json::from_value(services).unwrap(),
+        types: serde_json::from_value(types).unwrap(),
+    }
+}
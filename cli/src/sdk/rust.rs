@@ -0,0 +1,547 @@
+use super::write_file;
+use crate::services::{FieldMetadata, Metadata, ServiceMetadata, Shape, TypeMetadata, TypeRef};
+use std::{fs, io, path::Path};
+
+/// Kept in its own module so the names it needs can't collide with the user's types.
+const REMOTE_RS: &str = r#"
+use serde::Deserialize;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Http(reqwest::Error),
+    Serde(serde_json::Error),
+    Rpc {
+        code: i64,
+        message: String,
+        data: Option<serde_json::Value>,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Http(err) => write!(f, "request failed: {err}"),
+            Error::Serde(err) => write!(f, "invalid payload: {err}"),
+            Error::Rpc { code, message, .. } => write!(f, "{message} ({code})"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Serde(err)
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    #[serde(default)]
+    result: serde_json::Value,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Remote {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl Remote {
+    pub fn new(client: reqwest::Client, url: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into(),
+        }
+    }
+
+    pub async fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Vec<serde_json::Value>,
+    ) -> Result<T> {
+        let response: RpcResponse = self
+            .client
+            .post(&self.url)
+            .json(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": params,
+                "id": 1,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(error) = response.error {
+            return Err(Error::Rpc {
+                code: error.code,
+                message: error.message,
+                data: error.data,
+            });
+        }
+
+        Ok(serde_json::from_value(response.result)?)
+    }
+}
+"#;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "try",
+    "type", "unsafe", "use", "where", "while", "yield",
+];
+
+/// Methods of the generated clients, services can't have methods with these names.
+const CONSTRUCTORS: &[&str] = &["new", "with_client"];
+
+fn words(name: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+
+    for ch in name.chars() {
+        if !ch.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        if ch.is_uppercase() && word.chars().last().is_some_and(|c| c.is_lowercase()) {
+            words.push(std::mem::take(&mut word));
+        }
+
+        word.push(ch);
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+fn snake_ident(name: &str) -> String {
+    let ident = words(name)
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_");
+
+    match ident.as_str() {
+        "" => "_field".to_owned(),
+        _ if ident.starts_with(|ch: char| ch.is_numeric()) => format!("_{ident}"),
+        _ if RUST_KEYWORDS.contains(&ident.as_str()) => format!("r#{ident}"),
+        _ => ident,
+    }
+}
+
+fn pascal_ident(name: &str) -> String {
+    let ident = words(name)
+        .iter()
+        .map(|word| word[..1].to_uppercase() + &word[1..])
+        .collect::<String>();
+
+    match ident.as_str() {
+        "" => "Variant".to_owned(),
+        _ if ident.starts_with(|ch: char| ch.is_numeric()) => format!("V{ident}"),
+        _ => ident,
+    }
+}
+
+fn rust_type(ty: &TypeRef, generics: &[String]) -> String {
+    let (name, args) = match ty {
+        TypeRef::Tuple { tuple } if tuple.len() == 1 => {
+            return format!("({},)", rust_type(&tuple[0], generics))
+        }
+        TypeRef::Tuple { tuple } => {
+            let items = tuple
+                .iter()
+                .map(|ty| rust_type(ty, generics))
+                .collect::<Vec<_>>();
+            return format!("({})", items.join(", "));
+        }
+        TypeRef::Named { name, args } => (name.as_str(), args),
+    };
+
+    let arg = |i: usize| {
+        args.get(i)
+            .map(|ty| rust_type(ty, generics))
+            .unwrap_or_else(|| "serde_json::Value".to_owned())
+    };
+
+    match name {
+        _ if generics.iter().any(|g| g == name) => name.to_owned(),
+        "String" | "str" | "PathBuf" | "Uuid" | "DateTime" | "NaiveDate" | "NaiveDateTime"
+        | "NaiveTime" | "Decimal" => "String".to_owned(),
+        "char" | "bool" | "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16"
+        | "u32" | "u64" | "u128" | "usize" | "f32" | "f64" => name.to_owned(),
+        "Value" | "" => "serde_json::Value".to_owned(),
+        "Option" => format!("Option<{}>", arg(0)),
+        "Box" => format!("Box<{}>", arg(0)),
+        "Rc" | "Arc" | "Cow" => arg(0),
+        // sets and lists all travel as json arrays
+        "Vec" | "VecDeque" | "HashSet" | "BTreeSet" | "IndexSet" => format!("Vec<{}>", arg(0)),
+        "HashMap" | "BTreeMap" | "IndexMap" => {
+            let key = match arg(0).as_str() {
+                key @ ("char" | "bool" | "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8"
+                | "u16" | "u32" | "u64" | "u128" | "usize") => key.to_owned(),
+                _ => "String".to_owned(),
+            };
+            format!("std::collections::HashMap<{key}, {}>", arg(1))
+        }
+        _ if args.is_empty() => name.to_owned(),
+        _ => {
+            let args = (0..args.len()).map(arg).collect::<Vec<_>>();
+            format!("{name}<{}>", args.join(", "))
+        }
+    }
+}
+
+fn render_field(field: &FieldMetadata, generics: &[String], visibility: &str) -> String {
+    let ident = snake_ident(&field.name);
+    let ty = rust_type(&field.ty, generics);
+
+    let mut attrs = vec![];
+    if ident.trim_start_matches("r#") != field.name {
+        attrs.push(format!("rename = {:?}", field.name));
+    }
+    if field.flatten {
+        attrs.push("flatten".to_owned());
+    }
+    if field.optional && ty.starts_with("Option<") {
+        attrs.push("default, skip_serializing_if = \"Option::is_none\"".to_owned());
+    } else if field.optional && (ty.starts_with("Vec<") || ty == "String") {
+        attrs.push("default".to_owned());
+    }
+
+    let attrs = if attrs.is_empty() {
+        String::new()
+    } else {
+        format!("    #[serde({})]\n", attrs.join(", "))
+    };
+
+    format!("{attrs}    {visibility}{ident}: {ty},\n")
+}
+
+/// Renders the fields of a struct or enum variant.
+fn render_fields(shape: &Shape, generics: &[String], visibility: &str) -> String {
+    match shape {
+        Shape::Struct { fields } => {
+            let fields = fields
+                .iter()
+                .map(|field| render_field(field, generics, visibility))
+                .collect::<String>();
+            format!(" {{\n{fields}}}")
+        }
+        Shape::Tuple { items } => {
+            let items = items
+                .iter()
+                .map(|ty| format!("{visibility}{}", rust_type(ty, generics)))
+                .collect::<Vec<_>>();
+            format!("({})", items.join(", "))
+        }
+        Shape::Alias { ty } => format!("({visibility}{})", rust_type(ty, generics)),
+        Shape::Unit | Shape::Enum { .. } => String::new(),
+    }
+}
+
+fn render_type(ty: &TypeMetadata) -> String {
+    let ident = &ty.name;
+    let generics = if ty.generics.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", ty.generics.join(", "))
+    };
+
+    let derive = "#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]\n";
+
+    match &ty.shape {
+        Shape::Enum {
+            tag,
+            content,
+            untagged,
+            variants,
+        } => {
+            let mut attrs = vec![];
+            if let Some(tag) = tag {
+                attrs.push(format!("tag = {tag:?}"));
+            }
+            if let Some(content) = content {
+                attrs.push(format!("content = {content:?}"));
+            }
+            if *untagged {
+                attrs.push("untagged".to_owned());
+            }
+
+            let container = if attrs.is_empty() {
+                String::new()
+            } else {
+                format!("#[serde({})]\n", attrs.join(", "))
+            };
+
+            let variants = variants
+                .iter()
+                .map(|variant| {
+                    let variant_ident = pascal_ident(&variant.name);
+                    let rename = if variant_ident != variant.name {
+                        format!("    #[serde(rename = {:?})]\n", variant.name)
+                    } else {
+                        String::new()
+                    };
+
+                    let fields = render_fields(&variant.shape, &ty.generics, "")
+                        .replace("\n    ", "\n        ")
+                        .replace("\n}", "\n    }");
+
+                    format!("{rename}    {variant_ident}{fields},\n")
+                })
+                .collect::<String>();
+
+            format!("{derive}{container}pub enum {ident}{generics} {{\n{variants}}}\n")
+        }
+        shape => {
+            let fields = render_fields(shape, &ty.generics, "pub ");
+            let semicolon = if matches!(shape, Shape::Struct { .. }) {
+                ""
+            } else {
+                ";"
+            };
+
+            format!("{derive}pub struct {ident}{generics}{fields}{semicolon}\n")
+        }
+    }
+}
+
+fn render_service(service: &ServiceMetadata) -> io::Result<String> {
+    let methods = service
+        .methods
+        .iter()
+        .map(|method| {
+            let ident = snake_ident(&method.name);
+            if CONSTRUCTORS.contains(&ident.as_str()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "`{}.{}` has the name of a client constructor, rename the method",
+                        service.name, method.name
+                    ),
+                ));
+            }
+
+            let params = method
+                .params
+                .iter()
+                .map(|param| {
+                    format!(
+                        ", {}: {}",
+                        snake_ident(&param.name),
+                        rust_type(&param.ty, &[])
+                    )
+                })
+                .collect::<String>();
+
+            let args = method
+                .params
+                .iter()
+                .map(|param| format!("serde_json::to_value({})?", snake_ident(&param.name)))
+                .collect::<Vec<_>>();
+
+            let returns = method
+                .returns
+                .as_ref()
+                .map(|ty| rust_type(ty, &[]))
+                .unwrap_or_else(|| "()".to_owned());
+
+            Ok(format!(
+                "
+    pub async fn {ident}(&self{params}) -> remote::Result<{returns}> {{
+        self.remote
+            .call(\"{service}.{name}\", vec![{args}])
+            .await
+    }}
+",
+                name = method.name,
+                args = args.join(", "),
+                service = service.name,
+            ))
+        })
+        .collect::<io::Result<String>>()?;
+
+    Ok(format!(
+        "
+#[derive(Debug, Clone)]
+pub struct {name} {{
+    remote: remote::Remote,
+}}
+
+impl {name} {{
+    pub fn new(url: impl Into<String>) -> Self {{
+        Self::with_client(reqwest::Client::new(), url)
+    }}
+
+    pub fn with_client(client: reqwest::Client, url: impl Into<String>) -> Self {{
+        Self {{
+            remote: remote::Remote::new(client, url),
+        }}
+    }}
+{methods}}}
+",
+        name = pascal_ident(&service.name)
+    ))
+}
+
+fn render_cargo_toml(name: &str, standalone: bool) -> String {
+    // keep the crate out of the surrounding workspace when it lives in the target dir
+    let workspace = if standalone { "\n[workspace]\n" } else { "" };
+
+    format!(
+        r#"
+[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+reqwest = {{ version = "0.11", default-features = false, features = ["json", "rustls-tls"] }}
+serde = {{ version = "1.0", features = ["derive"] }}
+serde_json = "1.0"
+{workspace}"#
+    )
+}
+
+/// Writes a Rust client crate named `name` for the services into `dir`.
+pub fn write_sdk(dir: &Path, name: &str, metadata: &Metadata, standalone: bool) -> io::Result<()> {
+    fs::create_dir_all(dir.join("src"))?;
+
+    write_file(dir, "Cargo.toml", &render_cargo_toml(name, standalone))?;
+
+    let mut lib = String::from(
+        "//! Client for the genezio services, generated by `genezio-rs build`.\n\n#![allow(dead_code)]\n\npub mod remote;\n",
+    );
+
+    for ty in &metadata.types {
+        lib.push('\n');
+        lib.push_str(&render_type(ty));
+    }
+    for service in &metadata.services {
+        lib.push_str(&render_service(service)?);
+    }
+
+    write_file(&dir.join("src"), "remote.rs", REMOTE_RS)?;
+    write_file(&dir.join("src"), "lib.rs", &lib)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdk::example;
+
+    #[test]
+    fn renders_types() {
+        let types = example()
+            .types
+            .iter()
+            .map(render_type)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        assert_eq!(
+            types,
+            r#"#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct User {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub tags: Vec<String>,
+    pub scores: std::collections::HashMap<String, u32>,
+    pub position: (f64, f64),
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    Created {
+        id: u64,
+    },
+    Deleted,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum Status {
+    Active,
+    Banned(String),
+}
+"#
+        );
+    }
+
+    #[test]
+    fn renders_services() {
+        let service = render_service(&example().services[0]).unwrap();
+
+        assert!(service.contains("pub struct Users {\n    remote: remote::Remote,\n}"));
+        assert!(service.contains(
+            "    pub async fn get(&self, id: u64) -> remote::Result<Option<User>> {
+        self.remote
+            .call(\"Users.get\", vec![serde_json::to_value(id)?])
+            .await
+    }"
+        ));
+        assert!(service.contains("    pub async fn list(&self) -> remote::Result<Page<User>> {"));
+        assert!(service.contains("    pub async fn ping(&self) -> remote::Result<()> {"));
+    }
+
+    #[test]
+    fn rejects_methods_named_like_constructors() {
+        let mut service = example().services.remove(0);
+        service.methods[2].name = "withClient".to_owned();
+
+        let err = render_service(&service).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`Users.withClient` has the name of a client constructor, rename the method"
+        );
+    }
+
+    #[test]
+    fn keeps_the_runtime_out_of_the_crate_root() {
+        let dir = std::env::temp_dir().join(format!("genezio-rust-sdk-{}", std::process::id()));
+        let mut metadata = example();
+        metadata.types[0].name = "Error".to_owned();
+
+        write_sdk(&dir, "client", &metadata, true).unwrap();
+        let lib = fs::read_to_string(dir.join("src/lib.rs")).unwrap();
+        let remote = fs::read_to_string(dir.join("src/remote.rs")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(lib.contains("pub mod remote;"));
+        assert!(lib.contains("pub struct Error {"));
+        assert!(!lib.contains("pub enum Error"));
+        assert!(remote.contains("pub enum Error {"));
+    }
+}
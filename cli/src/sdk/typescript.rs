@@ -0,0 +1,338 @@
+use super::{file_name, write_file};
+use crate::services::{FieldMetadata, Metadata, ServiceMetadata, Shape, TypeMetadata, TypeRef};
+use std::{fs, io, path::Path};
+
+const REMOTE_TS: &str = r#"
+export class RpcError extends Error {
+  constructor(public code: number, message: string, public data?: unknown) {
+    super(message);
+  }
+}
+
+export class Remote {
+  private nextId = 1;
+
+  constructor(public url: string) {}
+
+  async call(method: string, ...params: unknown[]): Promise<any> {
+    const response = await fetch(this.url, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ jsonrpc: "2.0", method, params, id: this.nextId++ }),
+    });
+
+    if (!response.ok) {
+      throw new Error(`${method} failed with HTTP ${response.status}: ${await response.text()}`);
+    }
+
+    const body = await response.json();
+    if (body.error) {
+      throw new RpcError(body.error.code, body.error.message, body.error.data);
+    }
+
+    return body.result;
+  }
+}
+"#;
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_' || ch == '$')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '$')
+}
+
+fn key(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_owned()
+    } else {
+        format!("{name:?}")
+    }
+}
+
+fn array(inner: String) -> String {
+    if is_identifier(&inner) {
+        format!("{inner}[]")
+    } else {
+        format!("Array<{inner}>")
+    }
+}
+
+fn ts_type(ty: &TypeRef, generics: &[String]) -> String {
+    let (name, args) = match ty {
+        TypeRef::Tuple { tuple } if tuple.is_empty() => return "null".to_owned(),
+        TypeRef::Tuple { tuple } => {
+            let items = tuple
+                .iter()
+                .map(|ty| ts_type(ty, generics))
+                .collect::<Vec<_>>();
+            return format!("[{}]", items.join(", "));
+        }
+        TypeRef::Named { name, args } => (name.as_str(), args),
+    };
+
+    let arg = |i: usize| {
+        args.get(i)
+            .map(|ty| ts_type(ty, generics))
+            .unwrap_or_else(|| "unknown".to_owned())
+    };
+
+    match name {
+        _ if generics.iter().any(|g| g == name) => name.to_owned(),
+        "String" | "str" | "char" | "PathBuf" | "Uuid" | "DateTime" | "NaiveDate"
+        | "NaiveDateTime" | "NaiveTime" | "Decimal" => "string".to_owned(),
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+        | "usize" | "f32" | "f64" => "number".to_owned(),
+        "bool" => "boolean".to_owned(),
+        "Value" => "any".to_owned(),
+        "Option" => format!("{} | null", arg(0)),
+        "Vec" | "VecDeque" | "HashSet" | "BTreeSet" | "IndexSet" => array(arg(0)),
+        "Box" | "Rc" | "Arc" | "Cow" => arg(0),
+        "HashMap" | "BTreeMap" | "IndexMap" => format!("Record<string, {}>", arg(1)),
+        "" => "unknown".to_owned(),
+        _ if args.is_empty() => name.to_owned(),
+        _ => {
+            let args = (0..args.len()).map(arg).collect::<Vec<_>>();
+            format!("{name}<{}>", args.join(", "))
+        }
+    }
+}
+
+fn field(field: &FieldMetadata, generics: &[String]) -> String {
+    format!(
+        "{}{}: {}",
+        key(&field.name),
+        if field.optional { "?" } else { "" },
+        ts_type(&field.ty, generics)
+    )
+}
+
+/// Renders an object type, with flattened fields as intersections.
+fn object(fields: &[FieldMetadata], generics: &[String], indent: &str) -> String {
+    let (flattened, fields): (Vec<_>, Vec<_>) = fields.iter().partition(|field| field.flatten);
+
+    let object = if indent.is_empty() {
+        let fields = fields
+            .iter()
+            .map(|f| field(f, generics))
+            .collect::<Vec<_>>();
+        format!("{{ {} }}", fields.join("; "))
+    } else {
+        let fields = fields
+            .iter()
+            .map(|f| format!("{indent}{};\n", field(f, generics)))
+            .collect::<String>();
+        format!("{{\n{fields}}}")
+    };
+
+    flattened.iter().fold(object, |object, field| {
+        format!("{object} & {}", ts_type(&field.ty, generics))
+    })
+}
+
+fn shape(shape: &Shape, generics: &[String]) -> String {
+    match shape {
+        Shape::Struct { fields } => object(fields, generics, ""),
+        Shape::Tuple { items } => ts_type(
+            &TypeRef::Tuple {
+                tuple: items.clone(),
+            },
+            generics,
+        ),
+        Shape::Alias { ty } => ts_type(ty, generics),
+        Shape::Unit => "null".to_owned(),
+        Shape::Enum {
+            tag,
+            content,
+            untagged,
+            variants,
+        } => {
+            let variants = variants
+                .iter()
+                .map(|variant| {
+                    let name = format!("{:?}", variant.name);
+                    let unit = matches!(variant.shape, Shape::Unit);
+                    let data = || self::shape(&variant.shape, generics);
+
+                    match (untagged, tag, content) {
+                        (true, _, _) => data(),
+                        (false, Some(tag), Some(content)) if !unit => {
+                            format!("{{ {}: {name}; {}: {} }}", key(tag), key(content), data())
+                        }
+                        (false, Some(tag), _) if unit => format!("{{ {}: {name} }}", key(tag)),
+                        (false, Some(tag), _) => format!("{{ {}: {name} }} & {}", key(tag), data()),
+                        (false, None, _) if unit => name,
+                        (false, None, _) => format!("{{ {name}: {} }}", data()),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            if variants.is_empty() {
+                "never".to_owned()
+            } else {
+                variants.join(" | ")
+            }
+        }
+    }
+}
+
+fn render_type(ty: &TypeMetadata) -> String {
+    let generics = if ty.generics.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", ty.generics.join(", "))
+    };
+
+    match &ty.shape {
+        Shape::Struct { fields } if !fields.iter().any(|field| field.flatten) => format!(
+            "export interface {}{generics} {}\n",
+            ty.name,
+            object(fields, &ty.generics, "  ")
+        ),
+        other => format!(
+            "export type {}{generics} = {};\n",
+            ty.name,
+            shape(other, &ty.generics)
+        ),
+    }
+}
+
+fn render_types(types: &[TypeMetadata]) -> String {
+    if types.is_empty() {
+        return "export {};\n".to_owned();
+    }
+
+    types.iter().map(render_type).collect::<Vec<_>>().join("\n")
+}
+
+fn render_service(service: &ServiceMetadata, types: &[TypeMetadata]) -> String {
+    let imports = if types.is_empty() {
+        String::new()
+    } else {
+        let names = types.iter().map(|ty| ty.name.as_str()).collect::<Vec<_>>();
+        format!("import type {{ {} }} from \"./types\";\n", names.join(", "))
+    };
+
+    let methods = service
+        .methods
+        .iter()
+        .map(|method| {
+            let params = method
+                .params
+                .iter()
+                .map(|param| format!("{}: {}", param.name, ts_type(&param.ty, &[])))
+                .collect::<Vec<_>>();
+
+            let args = method
+                .params
+                .iter()
+                .map(|param| format!(", {}", param.name))
+                .collect::<String>();
+
+            let returns = method
+                .returns
+                .as_ref()
+                .map(|ty| ts_type(ty, &[]))
+                .unwrap_or_else(|| "void".to_owned());
+
+            format!(
+                "
+  static async {name}({params}): Promise<{returns}> {{
+    return await {service}.remote.call(\"{service}.{name}\"{args});
+  }}
+",
+                name = method.name,
+                params = params.join(", "),
+                service = service.name,
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        "
+import {{ Remote }} from \"./remote\";
+{imports}
+export class {name} {{
+  static remote = new Remote(\"\");
+{methods}}}
+",
+        name = service.name
+    )
+}
+
+/// Writes a typed TypeScript client for the services into `dir`.
+pub fn write_sdk(dir: &Path, metadata: &Metadata) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    write_file(dir, "remote.ts", REMOTE_TS)?;
+    write_file(dir, "types.ts", &render_types(&metadata.types))?;
+
+    let mut index = String::from("export * from \"./remote\";\nexport * from \"./types\";\n");
+    for service in &metadata.services {
+        let name = file_name(&service.name);
+        write_file(
+            dir,
+            &format!("{name}.ts"),
+            &render_service(service, &metadata.types),
+        )?;
+        index.push_str(&format!("export * from \"./{name}\";\n"));
+    }
+
+    write_file(dir, "index.ts", &index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdk::example;
+
+    #[test]
+    fn renders_types() {
+        assert_eq!(
+            render_types(&example().types),
+            r#"export interface User {
+  name: string;
+  email?: string | null;
+  tags: string[];
+  scores: Record<string, number>;
+  position: [number, number];
+}
+
+export interface Page<T> {
+  items: T[];
+}
+
+export type Event = { type: "Created" } & { id: number } | { type: "Deleted" };
+
+export type Status = "Active" | { "Banned": [string] };
+"#
+        );
+        assert_eq!(render_types(&[]), "export {};\n");
+    }
+
+    #[test]
+    fn renders_services() {
+        let metadata = example();
+        let service = render_service(&metadata.services[0], &metadata.types);
+
+        assert!(service.contains("import type { User, Page, Event, Status } from \"./types\";"));
+        assert!(service.contains(
+            "  static async get(id: number): Promise<User | null> {
+    return await Users.remote.call(\"Users.get\", id);
+  }"
+        ));
+        assert!(service.contains(
+            "  static async list(): Promise<Page<User>> {
+    return await Users.remote.call(\"Users.list\");
+  }"
+        ));
+        assert!(service.contains("  static async ping(): Promise<void> {"));
+    }
+
+    #[test]
+    fn checks_the_http_status_before_parsing() {
+        let ok = REMOTE_TS.find("response.ok").unwrap();
+        assert!(ok < REMOTE_TS.find("response.json()").unwrap());
+    }
+}
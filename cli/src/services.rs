@@ -0,0 +1,140 @@
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// Must match the markers emitted by `#[genezio::service]` and `#[derive(genezio::rpc::Type)]`.
+const SERVICE_MARKER: &[u8] = b"\0genezio-service\0";
+const TYPE_MARKER: &[u8] = b"\0genezio-type\0";
+
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    pub services: Vec<ServiceMetadata>,
+    pub types: Vec<TypeMetadata>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceMetadata {
+    pub name: String,
+    pub methods: Vec<MethodMetadata>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MethodMetadata {
+    pub name: String,
+    pub params: Vec<ParamMetadata>,
+    #[serde(default)]
+    pub returns: Option<TypeRef>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ParamMetadata {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: TypeRef,
+}
+
+/// A Rust type as written in the source, e.g. `Vec<User>`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TypeRef {
+    Tuple {
+        tuple: Vec<TypeRef>,
+    },
+    Named {
+        name: String,
+        #[serde(default)]
+        args: Vec<TypeRef>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TypeMetadata {
+    pub name: String,
+    pub generics: Vec<String>,
+    pub shape: Shape,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Shape {
+    Struct {
+        fields: Vec<FieldMetadata>,
+    },
+    Tuple {
+        items: Vec<TypeRef>,
+    },
+    Alias {
+        #[serde(rename = "type")]
+        ty: TypeRef,
+    },
+    Unit,
+    Enum {
+        tag: Option<String>,
+        content: Option<String>,
+        untagged: bool,
+        variants: Vec<VariantMetadata>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldMetadata {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: TypeRef,
+    pub optional: bool,
+    pub flatten: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VariantMetadata {
+    pub name: String,
+    pub shape: Shape,
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn read_entries<T: DeserializeOwned>(
+    bytes: &[u8],
+    marker: &[u8],
+) -> Result<Vec<T>, serde_json::Error> {
+    let mut entries = vec![];
+    let mut rest = bytes;
+
+    while let Some(start) = find(rest, marker) {
+        rest = &rest[start + marker.len()..];
+        let end = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+
+        entries.push(serde_json::from_slice(&rest[..end])?);
+        rest = &rest[end..];
+    }
+
+    Ok(entries)
+}
+
+impl Metadata {
+    /// Merges the metadata read from another binary, keeping the first entry for each name.
+    pub fn extend(&mut self, other: Metadata) {
+        self.services.extend(other.services);
+        self.services.sort_by(|a, b| a.name.cmp(&b.name));
+        self.services.dedup_by(|a, b| a.name == b.name);
+
+        self.types.extend(other.types);
+        self.types.sort_by(|a, b| a.name.cmp(&b.name));
+        self.types.dedup_by(|a, b| a.name == b.name);
+    }
+}
+
+/// Collects the service and type descriptions embedded in a compiled binary.
+pub fn read_metadata(bytes: &[u8]) -> Result<Metadata, serde_json::Error> {
+    let mut services: Vec<ServiceMetadata> = read_entries(bytes, SERVICE_MARKER)?;
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+    services.dedup_by(|a, b| a.name == b.name);
+
+    let mut types: Vec<TypeMetadata> = read_entries(bytes, TYPE_MARKER)?;
+    types.sort_by(|a, b| a.name.cmp(&b.name));
+    types.dedup_by(|a, b| a.name == b.name);
+
+    Ok(Metadata { services, types })
+}
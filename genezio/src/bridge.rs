@@ -0,0 +1,127 @@
+//! Serves the app to the generated JavaScript class over a unix socket.
+//!
+//! Every frame is a big endian `u32` length followed by a JSON payload. Requests carry an
+//! `id` that is echoed back in the response, so calls can be answered out of order.
+
+use axum::{
+    body::{to_bytes, Body},
+    http, Router,
+};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, io};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixStream,
+    sync::mpsc,
+};
+use tower::ServiceExt;
+
+/// Set by the JavaScript class to the path of the socket it listens on.
+pub const BRIDGE_ENV: &str = "GENEZIO_BRIDGE";
+
+#[derive(Debug, Deserialize)]
+struct RequestFrame {
+    id: u64,
+    method: String,
+    path: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    body: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ResponseFrame {
+    id: u64,
+    status: u16,
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+type DispatchError = Box<dyn std::error::Error + Send + Sync>;
+
+async fn dispatch(app: Router, frame: RequestFrame) -> Result<ResponseFrame, DispatchError> {
+    let body = general_purpose::STANDARD.decode(frame.body)?;
+
+    let mut request = http::Request::builder()
+        .method(frame.method.as_str())
+        .uri(frame.path);
+    for (name, value) in &frame.headers {
+        request = request.header(name, value);
+    }
+
+    let response = app.oneshot(request.body(Body::from(body))?).await?;
+
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+        .collect();
+    let body = to_bytes(response.into_body(), usize::MAX).await?;
+
+    Ok(ResponseFrame {
+        id: frame.id,
+        status,
+        headers,
+        body: general_purpose::STANDARD.encode(body),
+    })
+}
+
+async fn handle(app: Router, frame: RequestFrame) -> ResponseFrame {
+    let id = frame.id;
+
+    dispatch(app, frame)
+        .await
+        .unwrap_or_else(|err| ResponseFrame {
+            id,
+            status: 500,
+            headers: HashMap::new(),
+            body: general_purpose::STANDARD.encode(err.to_string()),
+        })
+}
+
+/// Connects to the socket at `path` and serves requests until the other side hangs up.
+pub async fn serve(path: &str, app: Router) -> io::Result<()> {
+    let stream = UnixStream::connect(path).await?;
+    let (mut reader, mut writer) = stream.into_split();
+    let (sender, mut receiver) = mpsc::unbounded_channel::<Vec<u8>>();
+
+    let writer_task = tokio::spawn(async move {
+        while let Some(frame) = receiver.recv().await {
+            writer.write_u32(frame.len() as u32).await?;
+            writer.write_all(&frame).await?;
+        }
+
+        Ok::<_, io::Error>(())
+    });
+
+    loop {
+        let len = match reader.read_u32().await {
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        let mut payload = vec![0; len as usize];
+        reader.read_exact(&mut payload).await?;
+
+        let frame: RequestFrame = serde_json::from_slice(&payload)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        let app = app.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            let response = handle(app, frame).await;
+            if let Ok(payload) = serde_json::to_vec(&response) {
+                let _ = sender.send(payload);
+            }
+        });
+    }
+
+    drop(sender);
+    writer_task.await??;
+
+    Ok(())
+}
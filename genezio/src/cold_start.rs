@@ -0,0 +1,74 @@
+//! Cold start phases reported by the generated `main` on Lambda.
+//!
+//! Every phase is printed on its own line as `genezio:cold-start {"phase":"...","ms":...}`, with
+//! `ms` the wall clock time in milliseconds since the Unix epoch, so the lines can be lined up
+//! with the ones printed by the generated JavaScript class (`binary_written` and
+//! `process_spawned`). `genezio-rs bench cold-start` reads them back.
+
+use axum::{extract::Request, middleware::Next, response::Response, Router};
+use serde::Serialize;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Prefix of the lines reporting a phase.
+pub const PREFIX: &str = "genezio:cold-start ";
+
+#[derive(Serialize)]
+struct PhaseLine {
+    phase: &'static str,
+    ms: f64,
+}
+
+static FIRST_REQUEST_SERVED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// The tokio runtime is ready.
+    RuntimeBuilt,
+    /// The app function returned the router.
+    RouterBuilt,
+    /// The router answered its first request.
+    FirstRequestServed,
+}
+
+impl Phase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::RuntimeBuilt => "runtime_built",
+            Phase::RouterBuilt => "router_built",
+            Phase::FirstRequestServed => "first_request_served",
+        }
+    }
+}
+
+/// Prints the line for `phase`.
+pub fn report(phase: Phase) {
+    let ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+        * 1000.0;
+
+    let line = PhaseLine {
+        phase: phase.as_str(),
+        ms,
+    };
+    if let Ok(line) = serde_json::to_string(&line) {
+        println!("{PREFIX}{line}");
+    }
+}
+
+async fn first_request(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    if !FIRST_REQUEST_SERVED.swap(true, Ordering::Relaxed) {
+        report(Phase::FirstRequestServed);
+    }
+    response
+}
+
+/// Reports [`Phase::FirstRequestServed`] once the router answers its first request.
+pub fn track_first_request(app: Router) -> Router {
+    app.layer(axum::middleware::from_fn(first_request))
+}
@@ -1,4 +1,15 @@
 pub use axum;
-pub use genezio_meta::app;
+pub use genezio_meta::{app, method, service};
 pub use lambda_http;
+pub use serde;
+pub use serde_json;
 pub use tokio;
+
+#[cfg(unix)]
+pub mod bridge;
+pub mod cold_start;
+pub mod panic;
+pub mod rpc;
+pub mod shutdown;
+#[cfg(feature = "testing")]
+pub mod testing;
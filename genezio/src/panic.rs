@@ -0,0 +1,104 @@
+//! Panic reports `genezio-rs symbolicate` can resolve.
+//!
+//! Binaries stripped by `genezio-rs build --optimize-size` print the frames of a backtrace as bare
+//! addresses, which also change with every run since the binary is loaded at a random address.
+//! Before the default hook prints the panic, the generated `main` prints a
+//! `genezio:panic {"build_id":"...","bias":"0x..."}` line, with the GNU build id of the binary and
+//! how far from its link time addresses it was loaded. Set `RUST_BACKTRACE=full` to get the frames,
+//! the short backtraces leave out the ones without symbols.
+
+use object::{
+    elf::{FileHeader64, NT_GNU_BUILD_ID, PT_LOAD},
+    read::elf::{FileHeader, ProgramHeader},
+    Endianness, ReadCache,
+};
+use serde::Serialize;
+use std::{env, fs, panic};
+
+/// Prefix of the line printed before a panic.
+pub const PREFIX: &str = "genezio:panic ";
+
+#[derive(Serialize)]
+struct PanicLine {
+    build_id: String,
+    bias: String,
+}
+
+/// The GNU build id of the executable and the link time address of its start. Only its headers
+/// are read, not the whole file.
+fn executable_headers() -> Option<(String, u64)> {
+    let file = fs::File::open(env::current_exe().ok()?).ok()?;
+    let cache = ReadCache::new(file);
+    let data = &cache;
+
+    let header = FileHeader64::<Endianness>::parse(data).ok()?;
+    let endian = header.endian().ok()?;
+
+    let mut build_id = None;
+    let mut first_address = None;
+    for segment in header.program_headers(endian, data).ok()? {
+        if segment.p_type(endian) == PT_LOAD && segment.p_offset(endian) == 0 {
+            first_address = Some(segment.p_vaddr(endian));
+        }
+
+        let Some(mut notes) = segment.notes(endian, data).ok()? else {
+            continue;
+        };
+        while let Some(note) = notes.next().ok()? {
+            if note.name() == b"GNU" && note.n_type(endian) == NT_GNU_BUILD_ID {
+                build_id = Some(
+                    note.desc()
+                        .iter()
+                        .map(|byte| format!("{byte:02x}"))
+                        .collect(),
+                );
+            }
+        }
+    }
+
+    Some((build_id?, first_address?))
+}
+
+/// Where the start of the executable is mapped, from `/proc/self/maps`.
+fn mapped_address() -> Option<u64> {
+    let exe = env::current_exe().ok()?;
+    let maps = fs::read_to_string("/proc/self/maps").ok()?;
+
+    maps.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let range = fields.next()?;
+        let offset = fields.nth(1)?;
+        let path = fields.nth(2)?;
+
+        if path != exe.to_str()? || u64::from_str_radix(offset, 16).ok()? != 0 {
+            return None;
+        }
+
+        u64::from_str_radix(range.split('-').next()?, 16).ok()
+    })
+}
+
+fn panic_line() -> Option<PanicLine> {
+    let (build_id, first_address) = executable_headers()?;
+    let bias = mapped_address()?.wrapping_sub(first_address);
+
+    Some(PanicLine {
+        build_id,
+        bias: format!("{bias:#x}"),
+    })
+}
+
+/// Prints the `genezio:panic` line before the hook that was set. The line is worked out here, the
+/// binary stays where it was loaded, so the hook only has to print it.
+pub fn install_hook() {
+    let line = panic_line().and_then(|line| serde_json::to_string(&line).ok());
+
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(line) = &line {
+            eprintln!("{PREFIX}{line}");
+        }
+
+        hook(info);
+    }));
+}
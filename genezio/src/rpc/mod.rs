@@ -0,0 +1,264 @@
+use axum::{body::Bytes, extract::State, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, fmt::Display, future::Future, pin::Pin, sync::Arc};
+
+mod types;
+
+pub use genezio_meta::Type;
+pub use types::Type;
+
+pub type MethodFuture = Pin<Box<dyn Future<Output = Result<Value, Error>> + Send>>;
+
+/// A set of methods callable over JSON-RPC. Implemented by `#[genezio::service]`.
+pub trait Service: Send + Sync + 'static {
+    /// Name of the genezio class, used as the prefix in `Class.method` calls.
+    const NAME: &'static str;
+
+    /// Service description embedded in the binary and read by `genezio-rs build`.
+    const METADATA: &'static str;
+
+    /// Adds the descriptions of the types used by the methods of this service.
+    fn types(types: &mut Vec<&'static str>);
+
+    fn call(self: Arc<Self>, method: &str, params: Value) -> MethodFuture;
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    pub jsonrpc: Option<String>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default)]
+    pub id: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Response {
+    pub jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Error>,
+    pub id: Value,
+}
+
+impl Response {
+    pub fn new(id: Value, result: Result<Value, Error>) -> Self {
+        match result {
+            Ok(result) => Self {
+                jsonrpc: "2.0",
+                result: Some(result),
+                error: None,
+                id,
+            },
+            Err(error) => Self {
+                jsonrpc: "2.0",
+                result: None,
+                error: Some(error),
+                id,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Error {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl Error {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const INTERNAL_ERROR: i64 = -32603;
+    pub const SERVER_ERROR: i64 = -32000;
+
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    pub fn parse_error(err: impl Display) -> Self {
+        Self::new(Self::PARSE_ERROR, format!("parse error: {err}"))
+    }
+
+    pub fn invalid_request(err: impl Display) -> Self {
+        Self::new(Self::INVALID_REQUEST, format!("invalid request: {err}"))
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(
+            Self::METHOD_NOT_FOUND,
+            format!("method not found: {method}"),
+        )
+    }
+
+    pub fn invalid_params(err: impl Display) -> Self {
+        Self::new(Self::INVALID_PARAMS, format!("invalid params: {err}"))
+    }
+
+    pub fn internal(err: impl Display) -> Self {
+        Self::new(Self::INTERNAL_ERROR, format!("internal error: {err}"))
+    }
+
+    /// Error returned by a method handler.
+    pub fn handler(err: impl Display) -> Self {
+        Self::new(Self::SERVER_ERROR, err.to_string())
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Deserializes JSON-RPC params into a tuple of method arguments. Params can be positional, or
+/// by name with `names` the names of the arguments; missing ones are `null` either way, so
+/// trailing `Option` arguments can be left out.
+#[doc(hidden)]
+pub fn params<T: serde::de::DeserializeOwned>(params: Value, names: &[&str]) -> Result<T, Error> {
+    let params = match params {
+        Value::Null => Value::Array(vec![]),
+        Value::Object(mut by_name) => {
+            let args = names
+                .iter()
+                .map(|name| by_name.remove(*name).unwrap_or(Value::Null))
+                .collect();
+
+            if let Some(name) = by_name.keys().next() {
+                return Err(Error::invalid_params(format!("unknown parameter `{name}`")));
+            }
+
+            Value::Array(args)
+        }
+        Value::Array(mut args) => {
+            if args.len() < names.len() {
+                args.resize(names.len(), Value::Null);
+            }
+
+            Value::Array(args)
+        }
+        params => params,
+    };
+
+    serde_json::from_value(params).map_err(Error::invalid_params)
+}
+
+/// Checks a method without arguments was called without params.
+#[doc(hidden)]
+pub fn no_params(params: Value) -> Result<(), Error> {
+    match params {
+        Value::Null => Ok(()),
+        Value::Array(args) if args.is_empty() => Ok(()),
+        Value::Object(args) if args.is_empty() => Ok(()),
+        _ => Err(Error::invalid_params("the method takes no parameters")),
+    }
+}
+
+/// Serializes a method's return value into a JSON-RPC result.
+#[doc(hidden)]
+pub fn result<T: Serialize>(value: T) -> Result<Value, Error> {
+    serde_json::to_value(value).map_err(Error::internal)
+}
+
+type Handler = Arc<dyn Fn(&str, Value) -> MethodFuture + Send + Sync>;
+
+/// Dispatches JSON-RPC requests to the registered services.
+#[derive(Clone, Default)]
+pub struct Rpc {
+    services: HashMap<&'static str, Handler>,
+}
+
+impl Rpc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a service. Panics if a service with the same name was already added, its methods
+    /// would be unreachable.
+    pub fn service<S: Service>(mut self, service: S) -> Self {
+        // keep the metadata in the final binary so the cli can find it
+        let mut metadata = vec![S::METADATA];
+        S::types(&mut metadata);
+        std::hint::black_box(&metadata);
+
+        let service = Arc::new(service);
+        let previous = self.services.insert(
+            S::NAME,
+            Arc::new(move |method, params| service.clone().call(method, params)),
+        );
+        assert!(
+            previous.is_none(),
+            "two services are named `{}`, services are named after their type so rename one",
+            S::NAME
+        );
+
+        self
+    }
+
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value, Error> {
+        let Some((service, name)) = method.split_once('.') else {
+            return Err(Error::method_not_found(method));
+        };
+
+        let Some(handler) = self.services.get(service) else {
+            return Err(Error::method_not_found(method));
+        };
+
+        handler(name, params).await
+    }
+
+    pub async fn handle(&self, request: Request) -> Response {
+        if request.jsonrpc.as_deref().is_some_and(|v| v != "2.0") {
+            return Response::new(
+                request.id,
+                Err(Error::invalid_request("unsupported jsonrpc version")),
+            );
+        }
+
+        let result = self.call(&request.method, request.params).await;
+        Response::new(request.id, result)
+    }
+
+    pub async fn handle_bytes(&self, body: &[u8]) -> Response {
+        let value: Value = match serde_json::from_slice(body) {
+            Ok(value) => value,
+            Err(err) => return Response::new(Value::Null, Err(Error::parse_error(err))),
+        };
+
+        let id = value.get("id").cloned().unwrap_or(Value::Null);
+        match serde_json::from_value::<Request>(value) {
+            Ok(request) => self.handle(request).await,
+            Err(err) => Response::new(id, Err(Error::invalid_request(err))),
+        }
+    }
+
+    /// Serves the registered services on `POST /`.
+    pub fn into_router(self) -> Router {
+        Router::new()
+            .route("/", post(handle_http))
+            .with_state(Arc::new(self))
+    }
+}
+
+async fn handle_http(State(rpc): State<Arc<Rpc>>, body: Bytes) -> Json<Response> {
+    Json(rpc.handle_bytes(&body).await)
+}
@@ -0,0 +1,161 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
+
+/// A type that can be used in service method signatures.
+///
+/// Implement it with `#[derive(genezio::rpc::Type)]` next to your serde derives. The derived
+/// descriptions are embedded in the binary and used by `genezio-rs build` to generate clients.
+pub trait Type {
+    /// Adds the descriptions of this type and the types it refers to.
+    fn collect(_types: &mut Vec<&'static str>) {}
+}
+
+macro_rules! impl_type {
+    ($($ty:ty),*) => {
+        $(impl Type for $ty {})*
+    };
+}
+
+impl_type!(
+    bool,
+    char,
+    str,
+    String,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    f32,
+    f64,
+    (),
+    serde_json::Value,
+    Path,
+    PathBuf
+);
+
+// the types the client generators know, for the crates that have them
+
+#[cfg(feature = "uuid")]
+impl_type!(uuid::Uuid);
+
+#[cfg(feature = "rust_decimal")]
+impl_type!(rust_decimal::Decimal);
+
+#[cfg(feature = "chrono")]
+impl_type!(chrono::NaiveDate, chrono::NaiveDateTime, chrono::NaiveTime);
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> Type for chrono::DateTime<Tz> {}
+
+macro_rules! impl_type_wrapper {
+    ($($ty:ident),*) => {
+        $(impl<T: Type + ?Sized> Type for $ty<T> {
+            fn collect(types: &mut Vec<&'static str>) {
+                T::collect(types);
+            }
+        })*
+    };
+}
+
+impl_type_wrapper!(Box, Rc, Arc);
+
+macro_rules! impl_type_collection {
+    ($($ty:ident),*) => {
+        $(impl<T: Type> Type for $ty<T> {
+            fn collect(types: &mut Vec<&'static str>) {
+                T::collect(types);
+            }
+        })*
+    };
+}
+
+impl_type_collection!(Option, Vec, VecDeque, BTreeSet);
+
+impl<T: Type, S> Type for HashSet<T, S> {
+    fn collect(types: &mut Vec<&'static str>) {
+        T::collect(types);
+    }
+}
+
+impl<T: Type> Type for [T] {
+    fn collect(types: &mut Vec<&'static str>) {
+        T::collect(types);
+    }
+}
+
+impl<T: Type, const N: usize> Type for [T; N] {
+    fn collect(types: &mut Vec<&'static str>) {
+        T::collect(types);
+    }
+}
+
+impl<T: Type + ?Sized> Type for &T {
+    fn collect(types: &mut Vec<&'static str>) {
+        T::collect(types);
+    }
+}
+
+impl<T: Type + ToOwned + ?Sized> Type for Cow<'_, T> {
+    fn collect(types: &mut Vec<&'static str>) {
+        T::collect(types);
+    }
+}
+
+impl<K: Type, V: Type, S> Type for HashMap<K, V, S> {
+    fn collect(types: &mut Vec<&'static str>) {
+        K::collect(types);
+        V::collect(types);
+    }
+}
+
+impl<K: Type, V: Type> Type for BTreeMap<K, V> {
+    fn collect(types: &mut Vec<&'static str>) {
+        K::collect(types);
+        V::collect(types);
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<T: Type, S> Type for indexmap::IndexSet<T, S> {
+    fn collect(types: &mut Vec<&'static str>) {
+        T::collect(types);
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K: Type, V: Type, S> Type for indexmap::IndexMap<K, V, S> {
+    fn collect(types: &mut Vec<&'static str>) {
+        K::collect(types);
+        V::collect(types);
+    }
+}
+
+macro_rules! impl_type_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: Type),*> Type for ($($name,)*) {
+            fn collect(types: &mut Vec<&'static str>) {
+                $($name::collect(types);)*
+            }
+        }
+    };
+}
+
+impl_type_tuple!(A);
+impl_type_tuple!(A, B);
+impl_type_tuple!(A, B, C);
+impl_type_tuple!(A, B, C, D);
+impl_type_tuple!(A, B, C, D, E);
+impl_type_tuple!(A, B, C, D, E, F);
@@ -0,0 +1,135 @@
+//! Shutdown signals for the generated `main`.
+//!
+//! Lambda only sends `SIGTERM` to the runtime before shutting it down when at least one extension
+//! is registered, so [`register_extension`] registers an internal one that doesn't subscribe to
+//! any events. Extensions can only register while the function initializes, so this only works
+//! for binaries that start with it, not the ones the JavaScript class starts on the first call.
+
+use std::io;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+const EXTENSION_NAME: &str = "genezio";
+
+/// Completes when the process receives `SIGINT` or `SIGTERM`.
+#[cfg(unix)]
+pub async fn signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let (Ok(mut interrupt), Ok(mut terminate)) = (
+        signal(SignalKind::interrupt()),
+        signal(SignalKind::terminate()),
+    ) else {
+        // without signal handlers there is nothing to wait for
+        return std::future::pending().await;
+    };
+
+    tokio::select! {
+        _ = interrupt.recv() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+/// Completes when the process receives Ctrl-C.
+#[cfg(not(unix))]
+pub async fn signal() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Sends a request to the Lambda runtime API and returns the response headers.
+async fn request(
+    api: &str,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> io::Result<Vec<(String, String)>> {
+    let mut stream = TcpStream::connect(api).await?;
+
+    let mut request = format!("{method} {path} HTTP/1.1\r\nHost: {api}\r\n");
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    request.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    ));
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status = String::new();
+    reader.read_line(&mut status).await?;
+
+    let mut headers = vec![];
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+        }
+    }
+
+    if !status
+        .split_whitespace()
+        .nth(1)
+        .is_some_and(|code| code.starts_with('2'))
+    {
+        let mut body = String::new();
+        reader.read_to_string(&mut body).await?;
+        return Err(io::Error::other(format!(
+            "{} {}",
+            status.trim(),
+            body.trim()
+        )));
+    }
+
+    Ok(headers)
+}
+
+/// Registers an internal Lambda extension so the runtime gets `SIGTERM` before it is shut down.
+/// Must be called before the runtime starts polling for events. Does nothing outside of Lambda.
+pub async fn register_extension() -> io::Result<()> {
+    let Ok(api) = std::env::var("AWS_LAMBDA_RUNTIME_API") else {
+        return Ok(());
+    };
+
+    let headers = request(
+        &api,
+        "POST",
+        "/2020-01-01/extension/register",
+        &[
+            ("Lambda-Extension-Name", EXTENSION_NAME),
+            ("Content-Type", "application/json"),
+        ],
+        r#"{"events":[]}"#,
+    )
+    .await?;
+
+    let id = headers
+        .into_iter()
+        .find(|(name, _)| name == "lambda-extension-identifier")
+        .map(|(_, value)| value)
+        .ok_or_else(|| io::Error::other("missing extension identifier"))?;
+
+    // extensions have to ask for the next event to finish initializing, and without any
+    // subscribed events the request only returns on shutdown
+    tokio::spawn(async move {
+        let _ = request(
+            &api,
+            "GET",
+            "/2020-01-01/extension/event/next",
+            &[("Lambda-Extension-Identifier", &id)],
+            "",
+        )
+        .await;
+    });
+
+    Ok(())
+}
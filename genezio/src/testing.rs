@@ -0,0 +1,492 @@
+//! Calls the `#[app]` router from tests without starting a server, either in-process or through
+//! `lambda_http` with the events Lambda sends, so both ways the app runs are covered by
+//! `cargo test`. Needs the `testing` feature.
+//!
+//! ```
+//! use genezio::{
+//!     axum::{routing::get, Router},
+//!     testing::TestClient,
+//! };
+//!
+//! fn app() -> Router {
+//!     Router::new().route("/health", get(|| async { "ok" }))
+//! }
+//!
+//! # genezio::tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let client = TestClient::new(app());
+//!
+//! let response = client.get("/health").send().await;
+//! assert_eq!(response.status(), 200);
+//! assert_eq!(response.text(), "ok");
+//!
+//! // the same request as a Function URL event, the way it runs on genezio
+//! let response = client.get("/health").send_function_url().await;
+//! assert_eq!(response.text(), "ok");
+//! # });
+//! ```
+//!
+//! The helpers panic when something goes wrong, like a body that isn't the expected JSON, so
+//! the test fails with a message saying why.
+//!
+//! The events are built like the ones `genezio-rs run --lambda` sends, the CLI doesn't depend on
+//! this crate so it has its own copy of the builders: both decode query strings with
+//! `form_urlencoded` and leave out the same fields.
+
+use crate::rpc;
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose, Engine as _};
+use lambda_http::{
+    lambda_runtime::{Context, LambdaEvent},
+    request::LambdaRequest,
+    Adapter,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Map, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tower::{Service, ServiceExt};
+
+/// Sends requests to an app.
+#[derive(Clone)]
+pub struct TestClient {
+    app: Router,
+}
+
+/// A request being built, sent with one of the `send` methods.
+#[must_use = "the request is only sent by one of the `send` methods"]
+pub struct TestRequest {
+    app: Router,
+    method: Method,
+    path: String,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+/// What the app answered.
+#[derive(Debug)]
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestClient {
+    pub fn new(app: Router) -> Self {
+        Self { app }
+    }
+
+    /// Starts a request for `path`, which can include a query string.
+    pub fn request(&self, method: Method, path: &str) -> TestRequest {
+        TestRequest {
+            app: self.app.clone(),
+            method,
+            path: path.to_owned(),
+            headers: HeaderMap::new(),
+            body: vec![],
+        }
+    }
+
+    pub fn get(&self, path: &str) -> TestRequest {
+        self.request(Method::GET, path)
+    }
+
+    pub fn post(&self, path: &str) -> TestRequest {
+        self.request(Method::POST, path)
+    }
+
+    pub fn put(&self, path: &str) -> TestRequest {
+        self.request(Method::PUT, path)
+    }
+
+    pub fn patch(&self, path: &str) -> TestRequest {
+        self.request(Method::PATCH, path)
+    }
+
+    pub fn delete(&self, path: &str) -> TestRequest {
+        self.request(Method::DELETE, path)
+    }
+
+    /// Starts a JSON-RPC call of a `#[service]` method, like `Hello.greet`, to the services
+    /// served by [`rpc::Rpc::into_router`].
+    pub fn rpc(&self, method: &str, params: impl Serialize) -> TestRequest {
+        let params = serde_json::to_value(params).expect("the params can't be serialized");
+
+        self.post("/").json(&json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": 1,
+        }))
+    }
+
+    /// Sends an event the way Lambda does, through `lambda_http`. Use it for events the request
+    /// builders can't make, the ones they make are available from
+    /// [`TestRequest::function_url_event`] and [`TestRequest::api_gateway_event`].
+    pub async fn send_event(&self, event: Value) -> TestResponse {
+        let request = serde_json::from_value::<LambdaRequest>(event)
+            .expect("the event isn't one lambda_http understands");
+
+        let mut adapter = Adapter::from(self.app.clone());
+        let response = adapter
+            .ready()
+            .await
+            .unwrap_or_else(|err| match err {})
+            .call(LambdaEvent::new(request, Context::default()))
+            .await
+            .unwrap_or_else(|err| match err {});
+
+        let response =
+            serde_json::to_value(response).expect("the lambda_http response can't be serialized");
+        TestResponse::from_lambda(&response)
+    }
+}
+
+impl TestRequest {
+    /// Adds a header, panics if the name or the value isn't valid in a header.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let name = HeaderName::try_from(name).expect("invalid header name");
+        let value = HeaderValue::try_from(value).expect("invalid header value");
+        self.headers.append(name, value);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Sets the body to `value` as JSON, with a matching `Content-Type`.
+    pub fn json(mut self, value: &impl Serialize) -> Self {
+        self.body = serde_json::to_vec(value).expect("the body can't be serialized");
+        self.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        self
+    }
+
+    /// Calls the router directly, the way it runs locally.
+    pub async fn send(self) -> TestResponse {
+        let mut request = Request::builder().method(self.method).uri(self.path);
+        if let Some(headers) = request.headers_mut() {
+            headers.extend(self.headers);
+        }
+        let request = request
+            .body(Body::from(self.body))
+            .expect("invalid request");
+
+        let response = self
+            .app
+            .oneshot(request)
+            .await
+            .unwrap_or_else(|err| match err {});
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("failed to read the response body");
+
+        TestResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    /// Sends the request as a Function URL event through `lambda_http`, the way it runs on
+    /// genezio.
+    pub async fn send_function_url(self) -> TestResponse {
+        let event = self.function_url_event();
+        TestClient::new(self.app).send_event(event).await
+    }
+
+    /// Sends the request as an API Gateway REST API event through `lambda_http`.
+    pub async fn send_api_gateway(self) -> TestResponse {
+        let event = self.api_gateway_event();
+        TestClient::new(self.app).send_event(event).await
+    }
+
+    fn split_path(&self) -> (&str, &str) {
+        self.path.split_once('?').unwrap_or((&self.path, ""))
+    }
+
+    fn query_parameters(&self) -> Vec<(String, String)> {
+        form_urlencoded::parse(self.split_path().1.as_bytes())
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect()
+    }
+
+    /// Adds the body to an event, like Lambda leaving it out when the request has none.
+    fn add_body(&self, event: &mut Value) {
+        if self.body.is_empty() {
+            return;
+        }
+
+        match std::str::from_utf8(&self.body) {
+            Ok(text) => event["body"] = json!(text),
+            Err(_) => {
+                event["body"] = json!(general_purpose::STANDARD.encode(&self.body));
+                event["isBase64Encoded"] = json!(true);
+            }
+        }
+    }
+
+    /// The Function URL event Lambda would send for this request. Cookies go to `cookies`,
+    /// repeated headers and query parameters are joined with commas.
+    pub fn function_url_event(&self) -> Value {
+        let (path, query) = self.split_path();
+
+        let mut headers = Map::new();
+        let mut cookies = vec![];
+        for (name, value) in &self.headers {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            if name == header::COOKIE {
+                cookies.extend(
+                    value
+                        .split(';')
+                        .map(str::trim)
+                        .filter(|cookie| !cookie.is_empty())
+                        .map(str::to_owned),
+                );
+            } else {
+                insert_joined(&mut headers, name.as_str(), value);
+            }
+        }
+
+        let mut parameters = Map::new();
+        for (key, value) in self.query_parameters() {
+            insert_joined(&mut parameters, &key, value);
+        }
+
+        let now = now_ms();
+        let mut event = json!({
+            "version": "2.0",
+            "routeKey": "$default",
+            "rawPath": path,
+            "rawQueryString": query,
+            "headers": headers,
+            "requestContext": {
+                "accountId": "anonymous",
+                "apiId": "genezio-test",
+                "domainName": "localhost",
+                "domainPrefix": "localhost",
+                "http": {
+                    "method": self.method.as_str(),
+                    "path": path,
+                    "protocol": "HTTP/1.1",
+                    "sourceIp": "127.0.0.1",
+                    "userAgent": "genezio-test",
+                },
+                "requestId": format!("{now:x}"),
+                "routeKey": "$default",
+                "stage": "$default",
+                "time": "",
+                "timeEpoch": now,
+            },
+            "isBase64Encoded": false,
+        });
+
+        if !cookies.is_empty() {
+            event["cookies"] = json!(cookies);
+        }
+        if !parameters.is_empty() {
+            event["queryStringParameters"] = Value::Object(parameters);
+        }
+        self.add_body(&mut event);
+
+        event
+    }
+
+    /// The API Gateway REST API (proxy integration) event Lambda would send for this request.
+    pub fn api_gateway_event(&self) -> Value {
+        let (path, _) = self.split_path();
+
+        let mut headers = Map::new();
+        let mut multi_value_headers = Map::<String, Value>::new();
+        for (name, value) in &self.headers {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            headers.insert(name.to_string(), json!(value));
+            push_multi(&mut multi_value_headers, name.as_str(), value);
+        }
+
+        let mut parameters = Map::new();
+        let mut multi_value_parameters = Map::new();
+        for (key, value) in self.query_parameters() {
+            parameters.insert(key.clone(), json!(value));
+            push_multi(&mut multi_value_parameters, &key, value);
+        }
+
+        let now = now_ms();
+        let mut event = json!({
+            "resource": "/{proxy+}",
+            "path": path,
+            "httpMethod": self.method.as_str(),
+            "headers": headers,
+            "multiValueHeaders": multi_value_headers,
+            "pathParameters": { "proxy": path.trim_start_matches('/') },
+            "requestContext": {
+                "accountId": "anonymous",
+                "apiId": "genezio-test",
+                "resourceId": "proxy",
+                "resourcePath": "/{proxy+}",
+                "httpMethod": self.method.as_str(),
+                "path": path,
+                "protocol": "HTTP/1.1",
+                // any other stage would be added in front of the path by lambda_http
+                "stage": "$default",
+                "requestId": format!("{now:x}"),
+                "requestTimeEpoch": now,
+                "identity": { "sourceIp": "127.0.0.1", "userAgent": "genezio-test" },
+            },
+            "isBase64Encoded": false,
+        });
+
+        if !parameters.is_empty() {
+            event["queryStringParameters"] = Value::Object(parameters);
+            event["multiValueQueryStringParameters"] = Value::Object(multi_value_parameters);
+        }
+        self.add_body(&mut event);
+
+        event
+    }
+}
+
+impl TestResponse {
+    /// Reads a response serialized by `lambda_http`.
+    fn from_lambda(response: &Value) -> Self {
+        let status = response["statusCode"]
+            .as_u64()
+            .and_then(|status| u16::try_from(status).ok())
+            .and_then(|status| StatusCode::from_u16(status).ok())
+            .expect("the lambda_http response has no valid statusCode");
+
+        let mut headers = HeaderMap::new();
+        let mut append = |name: &str, value: &Value| {
+            if let (Ok(name), Some(Ok(value))) = (
+                HeaderName::try_from(name),
+                value.as_str().map(HeaderValue::try_from),
+            ) {
+                headers.append(name, value);
+            }
+        };
+
+        if let Some(multi_value_headers) = response["multiValueHeaders"].as_object() {
+            for (name, values) in multi_value_headers {
+                for value in values.as_array().into_iter().flatten() {
+                    append(name, value);
+                }
+            }
+        }
+        if let Some(single_headers) = response["headers"].as_object() {
+            for (name, value) in single_headers {
+                // the same headers are often in both maps
+                if !multi_value_headers_contain(response, name) {
+                    append(name, value);
+                }
+            }
+        }
+        for cookie in response["cookies"].as_array().into_iter().flatten() {
+            append(header::SET_COOKIE.as_str(), cookie);
+        }
+
+        let body = response["body"].as_str().unwrap_or_default();
+        let body = if response["isBase64Encoded"].as_bool().unwrap_or(false) {
+            general_purpose::STANDARD
+                .decode(body)
+                .expect("the lambda_http response body isn't valid base64")
+        } else {
+            body.as_bytes().to_vec()
+        };
+
+        Self {
+            status,
+            headers,
+            body: Bytes::from(body),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The first value of a header, `None` when it's missing or not text.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Deserializes the body, panics if it isn't a `T` in JSON.
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body).unwrap_or_else(|err| {
+            panic!(
+                "the response body isn't the expected JSON: {err}\n{}",
+                self.text()
+            )
+        })
+    }
+
+    /// The result of a JSON-RPC call made with [`TestClient::rpc`], or the error it returned.
+    pub fn rpc<T: DeserializeOwned>(&self) -> Result<T, rpc::Error> {
+        let response = self.json::<Value>();
+
+        if let Some(error) = response.get("error") {
+            return Err(serde_json::from_value(error.clone())
+                .unwrap_or_else(|err| panic!("invalid JSON-RPC error: {err}\n{error}")));
+        }
+
+        let result = response.get("result").cloned().unwrap_or(Value::Null);
+        Ok(serde_json::from_value(result.clone())
+            .unwrap_or_else(|err| panic!("unexpected JSON-RPC result: {err}\n{result}")))
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Appends `value` to the one already under `key`, after a comma.
+fn insert_joined(map: &mut Map<String, Value>, key: &str, value: String) {
+    match map.get_mut(key) {
+        Some(Value::String(existing)) => {
+            existing.push(',');
+            existing.push_str(&value);
+        }
+        _ => {
+            map.insert(key.to_owned(), Value::String(value));
+        }
+    }
+}
+
+/// Appends `value` to the list under `key`.
+fn push_multi(map: &mut Map<String, Value>, key: &str, value: String) {
+    match map.get_mut(key) {
+        Some(Value::Array(values)) => values.push(json!(value)),
+        _ => {
+            map.insert(key.to_owned(), json!([value]));
+        }
+    }
+}
+
+fn multi_value_headers_contain(response: &Value, name: &str) -> bool {
+    response["multiValueHeaders"]
+        .as_object()
+        .is_some_and(|headers| headers.keys().any(|key| key.eq_ignore_ascii_case(name)))
+}
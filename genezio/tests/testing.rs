@@ -0,0 +1,167 @@
+use genezio::{
+    axum::{
+        extract::Query,
+        http::{header, HeaderMap},
+        response::{AppendHeaders, IntoResponse},
+        routing::get,
+        Json, Router,
+    },
+    rpc::Rpc,
+    serde_json::{json, Value},
+    service,
+    testing::{TestClient, TestRequest, TestResponse},
+};
+use std::{collections::BTreeMap, future::Future};
+
+struct Hello;
+
+#[service]
+impl Hello {
+    #[method]
+    fn greet(name: String) -> String {
+        format!("Hello, {name}!")
+    }
+}
+
+/// The query parameters by name, API Gateway only keeps the order of the values of each one.
+async fn query(Query(mut pairs): Query<Vec<(String, String)>>) -> Json<Vec<(String, String)>> {
+    pairs.sort_by(|a, b| a.0.cmp(&b.0));
+    Json(pairs)
+}
+
+/// The values of `x-tag`, which Function URLs join with commas.
+async fn headers(headers: HeaderMap) -> impl IntoResponse {
+    let tags = headers
+        .get_all("x-tag")
+        .iter()
+        .flat_map(|value| value.to_str().unwrap().split(','))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+
+    (
+        AppendHeaders([
+            (header::VARY, "accept"),
+            (header::VARY, "origin"),
+            (header::SET_COOKIE, "a=1; Path=/"),
+            (header::SET_COOKIE, "b=2"),
+        ]),
+        Json(tags),
+    )
+}
+
+fn app() -> Router {
+    Rpc::new()
+        .service(Hello)
+        .into_router()
+        .route("/query", get(query))
+        .route("/headers", get(headers))
+}
+
+/// Status, headers and body of a response. Function URLs join repeated headers with commas, so
+/// the values of every header but `Set-Cookie` are split on them.
+fn seen(response: &TestResponse) -> (u16, BTreeMap<String, Vec<String>>, Vec<u8>) {
+    let mut headers = BTreeMap::<String, Vec<String>>::new();
+    for (name, value) in response.headers() {
+        let value = value.to_str().unwrap();
+        let values = headers.entry(name.to_string()).or_default();
+        if name == header::SET_COOKIE {
+            values.push(value.to_owned());
+        } else {
+            values.extend(value.split(',').map(|value| value.trim().to_owned()));
+        }
+    }
+
+    (
+        response.status().as_u16(),
+        headers,
+        response.bytes().to_vec(),
+    )
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    genezio::tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// Sends the request every way and checks the app answers the same, returning that answer.
+async fn send_every_way(request: impl Fn() -> TestRequest) -> TestResponse {
+    let direct = request().send().await;
+    let function_url = request().send_function_url().await;
+    let api_gateway = request().send_api_gateway().await;
+
+    assert_eq!(seen(&direct), seen(&function_url), "Function URL");
+    assert_eq!(seen(&direct), seen(&api_gateway), "API Gateway");
+
+    direct
+}
+
+#[test]
+fn rpc_calls() {
+    let client = TestClient::new(app());
+
+    let response = block_on(send_every_way(|| client.rpc("Hello.greet", ["genezio"])));
+    assert_eq!(response.rpc::<String>().unwrap(), "Hello, genezio!");
+
+    let response = block_on(send_every_way(|| client.rpc("Hello.missing", json!([]))));
+    assert!(response.rpc::<Value>().is_err());
+}
+
+#[test]
+fn query_strings() {
+    let client = TestClient::new(app());
+
+    let response = block_on(send_every_way(|| {
+        client.get("/query?tag=a&tag=b+c&q=%3D%26&empty")
+    }));
+    assert_eq!(
+        response.json::<Vec<(String, String)>>(),
+        [
+            ("empty".to_owned(), String::new()),
+            ("q".to_owned(), "=&".to_owned()),
+            ("tag".to_owned(), "a".to_owned()),
+            ("tag".to_owned(), "b c".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn repeated_headers() {
+    let client = TestClient::new(app());
+
+    let response = block_on(send_every_way(|| {
+        client
+            .get("/headers")
+            .header("x-tag", "a")
+            .header("x-tag", "b")
+    }));
+
+    let (_, headers, _) = seen(&response);
+    assert_eq!(headers["vary"], ["accept", "origin"]);
+    assert_eq!(headers["set-cookie"], ["a=1; Path=/", "b=2"]);
+    assert_eq!(response.json::<Vec<String>>(), ["a", "b"]);
+}
+
+#[test]
+fn events_decode_query_strings() {
+    let client = TestClient::new(app());
+    let request = client.get("/query?tag=a&tag=b+c&q=%3D%26");
+
+    let event = request.function_url_event();
+    assert_eq!(event["rawQueryString"], "tag=a&tag=b+c&q=%3D%26");
+    assert_eq!(
+        event["queryStringParameters"],
+        json!({ "tag": "a,b c", "q": "=&" })
+    );
+
+    let event = request.api_gateway_event();
+    assert_eq!(
+        event["queryStringParameters"],
+        json!({ "tag": "b c", "q": "=&" })
+    );
+    assert_eq!(
+        event["multiValueQueryStringParameters"],
+        json!({ "tag": ["a", "b c"], "q": ["=&"] })
+    );
+}
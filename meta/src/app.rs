@@ -0,0 +1,352 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, FnArg, ItemFn, ReturnType, Signature, Type};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Flavor {
+    CurrentThread,
+    MultiThread,
+}
+
+impl Flavor {
+    fn parse(lit: &syn::LitStr) -> syn::Result<Self> {
+        match lit.value().as_str() {
+            "current_thread" => Ok(Flavor::CurrentThread),
+            "multi_thread" => Ok(Flavor::MultiThread),
+            _ => Err(syn::Error::new(
+                lit.span(),
+                "unknown runtime flavor, expected `current_thread` or `multi_thread`",
+            )),
+        }
+    }
+
+    fn builder(self) -> TokenStream {
+        match self {
+            Flavor::CurrentThread => {
+                quote! { genezio::tokio::runtime::Builder::new_current_thread() }
+            }
+            Flavor::MultiThread => quote! { genezio::tokio::runtime::Builder::new_multi_thread() },
+        }
+    }
+}
+
+/// Where the app listens when it runs outside of genezio (`HOST` and `PORT` override these), the
+/// async function to run before the process exits and how the tokio runtime is set up.
+struct AppArgs {
+    host: String,
+    port: u16,
+    on_shutdown: Option<syn::Path>,
+    flavor: Option<Flavor>,
+    worker_threads: Option<usize>,
+    thread_name: Option<String>,
+}
+
+impl AppArgs {
+    fn parse(attr: TokenStream) -> syn::Result<Self> {
+        let mut args = AppArgs {
+            host: "127.0.0.1".to_owned(),
+            port: 3000,
+            on_shutdown: None,
+            flavor: None,
+            worker_threads: None,
+            thread_name: None,
+        };
+        let mut flavor_span = None;
+
+        let parser = syn::meta::parser(|meta| {
+            if meta.path.is_ident("host") {
+                args.host = meta.value()?.parse::<syn::LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("port") {
+                args.port = meta.value()?.parse::<syn::LitInt>()?.base10_parse()?;
+                Ok(())
+            } else if meta.path.is_ident("on_shutdown") {
+                args.on_shutdown = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("flavor") {
+                let lit = meta.value()?.parse::<syn::LitStr>()?;
+                args.flavor = Some(Flavor::parse(&lit)?);
+                flavor_span = Some(lit.span());
+                Ok(())
+            } else if meta.path.is_ident("worker_threads") {
+                let lit = meta.value()?.parse::<syn::LitInt>()?;
+                match lit.base10_parse()? {
+                    0 => Err(syn::Error::new(
+                        lit.span(),
+                        "`worker_threads` must be at least 1",
+                    )),
+                    threads => {
+                        args.worker_threads = Some(threads);
+                        Ok(())
+                    }
+                }
+            } else if meta.path.is_ident("thread_name") {
+                args.thread_name = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error(
+                    "unknown `app` argument, expected `host`, `port`, `on_shutdown`, `flavor`, \
+                     `worker_threads` or `thread_name`",
+                ))
+            }
+        });
+
+        syn::parse::Parser::parse2(parser, attr)?;
+
+        if let (Some(Flavor::CurrentThread), Some(span), Some(_)) =
+            (args.flavor, flavor_span, args.worker_threads)
+        {
+            return Err(syn::Error::new(
+                span,
+                "`worker_threads` can't be used with the `current_thread` flavor",
+            ));
+        }
+
+        Ok(args)
+    }
+}
+
+/// Whether the app function returns a `Result` (`std`, `anyhow`, `io` or any other alias).
+fn returns_result(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+
+    path.path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "Result")
+}
+
+/// Checks that the app function can be called from the generated `main`, reporting every
+/// problem at once.
+fn check_signature(sig: &Signature) -> syn::Result<&Type> {
+    let mut errors = vec![];
+
+    if sig.ident == "main" {
+        errors.push(syn::Error::new(
+            sig.ident.span(),
+            "the `#[app]` function can't be called `main`, `#[app]` generates `main` for you",
+        ));
+    }
+
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        errors.push(syn::Error::new_spanned(
+            &sig.generics,
+            "the `#[app]` function can't be generic",
+        ));
+    }
+
+    if let Some(unsafety) = &sig.unsafety {
+        errors.push(syn::Error::new_spanned(
+            unsafety,
+            "the `#[app]` function can't be `unsafe`",
+        ));
+    }
+
+    if let Some(abi) = &sig.abi {
+        errors.push(syn::Error::new_spanned(
+            abi,
+            "the `#[app]` function can't have an `extern` ABI",
+        ));
+    }
+
+    for input in &sig.inputs {
+        let message = match input {
+            FnArg::Receiver(_) => {
+                "`#[app]` can't be used on methods, use a free function returning the `Router`"
+            }
+            FnArg::Typed(_) => "the `#[app]` function can't take arguments",
+        };
+        errors.push(syn::Error::new_spanned(input, message));
+    }
+
+    if let Some(variadic) = &sig.variadic {
+        errors.push(syn::Error::new_spanned(
+            variadic,
+            "the `#[app]` function can't take arguments",
+        ));
+    }
+
+    let returns = match &sig.output {
+        ReturnType::Type(_, ty) => Ok(ty.as_ref()),
+        ReturnType::Default => Err(syn::Error::new(
+            sig.paren_token.span.join(),
+            "the `#[app]` function must return an `axum::Router`, or a `Result` with one",
+        )),
+    };
+
+    let errors = errors.into_iter().reduce(|mut errors, error| {
+        errors.combine(error);
+        errors
+    });
+
+    match (errors, returns) {
+        (None, returns) => returns,
+        (Some(mut errors), Err(error)) => {
+            errors.combine(error);
+            Err(errors)
+        }
+        (Some(errors), Ok(_)) => Err(errors),
+    }
+}
+
+fn expand_fn(args: AppArgs, item_fn: &ItemFn) -> syn::Result<TokenStream> {
+    let returns = check_signature(&item_fn.sig)?;
+
+    let item_fn_name = &item_fn.sig.ident;
+    let host = &args.host;
+    let port = args.port;
+    let on_shutdown = args.on_shutdown.map(|hook| quote! { #hook().await; });
+
+    // lambdas mostly get a single vCPU, where a current thread runtime starts faster
+    let (lambda_flavor, local_flavor) = match (args.flavor, args.worker_threads) {
+        (Some(flavor), _) => (flavor, flavor),
+        (None, Some(_)) => (Flavor::MultiThread, Flavor::MultiThread),
+        (None, None) => (Flavor::CurrentThread, Flavor::MultiThread),
+    };
+    let lambda_builder = lambda_flavor.builder();
+    let local_builder = local_flavor.builder();
+    let worker_threads = args
+        .worker_threads
+        .map(|threads| quote! { builder.worker_threads(#threads); });
+    let thread_name = args
+        .thread_name
+        .map(|name| quote! { builder.thread_name(#name); });
+
+    let call = if item_fn.sig.asyncness.is_some() {
+        quote! { runtime.block_on(#item_fn_name()) }
+    } else {
+        quote! { #item_fn_name() }
+    };
+
+    let build_app = if returns_result(returns) {
+        quote! {
+            match #call {
+                Ok(app) => app,
+                Err(err) => {
+                    eprintln!("failed to build the app: {err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+    } else {
+        call
+    };
+
+    // point type errors at the return type instead of the generated code
+    let app = quote_spanned! {returns.span()=>
+        let app: genezio::axum::Router = #build_app;
+    };
+
+    Ok(quote! {
+        // `genezio_with_lambda` is set by `genezio-rs build`, cargo doesn't know about it
+        #[allow(unexpected_cfgs)]
+        pub fn main() {
+            #[cfg(genezio_with_lambda)]
+            genezio::panic::install_hook();
+            #[cfg(genezio_with_lambda)]
+            let mut builder = #lambda_builder;
+            #[cfg(not(genezio_with_lambda))]
+            let mut builder = #local_builder;
+            builder.enable_all();
+            #worker_threads
+            #thread_name
+            let runtime = builder.build().unwrap();
+            #[cfg(genezio_with_lambda)]
+            genezio::cold_start::report(genezio::cold_start::Phase::RuntimeBuilt);
+
+            #app
+
+            #[cfg(genezio_with_lambda)]
+            let app = {
+                genezio::cold_start::report(genezio::cold_start::Phase::RouterBuilt);
+                genezio::cold_start::track_first_request(app)
+            };
+
+            #[cfg(genezio_with_lambda)]
+            {
+                // the JavaScript class starts the binary on the first call, after the init phase
+                // where extensions can register, so Lambda doesn't send it `SIGTERM`
+                if let Ok(path) = std::env::var(genezio::bridge::BRIDGE_ENV) {
+                    runtime.block_on(async {
+                        genezio::tokio::select! {
+                            result = genezio::bridge::serve(&path, app) => result.unwrap(),
+                            _ = genezio::shutdown::signal() => {}
+                        }
+                        #on_shutdown
+                    });
+                    return;
+                }
+
+                runtime.block_on(async {
+                    if let Err(err) = genezio::shutdown::register_extension().await {
+                        eprintln!("failed to register the shutdown extension: {err}");
+                    }
+
+                    genezio::tokio::select! {
+                        result = genezio::lambda_http::run(app) => result.unwrap(),
+                        _ = genezio::shutdown::signal() => {}
+                    }
+                    #on_shutdown
+                });
+            }
+
+            #[cfg(not(genezio_with_lambda))]
+            {
+                runtime.block_on(async {
+                    let host = std::env::var("HOST").unwrap_or_else(|_| #host.to_owned());
+                    let port = match std::env::var("PORT") {
+                        Ok(port) => port.parse::<u16>().expect("PORT must be a port number"),
+                        Err(_) => #port,
+                    };
+
+                    let listener = genezio::tokio::net::TcpListener::bind((host.as_str(), port))
+                        .await
+                        .unwrap();
+                    println!("listening on {}", listener.local_addr().unwrap());
+
+                    genezio::axum::serve(listener, app)
+                        .with_graceful_shutdown(genezio::shutdown::signal())
+                        .await
+                        .unwrap();
+                    #on_shutdown
+                });
+            }
+        }
+
+        #item_fn
+    })
+}
+
+pub fn expand(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item_fn = match syn::parse2::<ItemFn>(item.clone()) {
+        Ok(item_fn) => item_fn,
+        Err(_) => {
+            let error = syn::Error::new_spanned(
+                &item,
+                "`#[app]` must be put on a function returning an `axum::Router`",
+            )
+            .into_compile_error();
+
+            return quote! {
+                #error
+                fn main() {}
+                #item
+            };
+        }
+    };
+
+    AppArgs::parse(attr)
+        .and_then(|args| expand_fn(args, &item_fn))
+        .unwrap_or_else(|err| {
+            // keep a `main` around so the only errors are the ones about the function
+            let err = err.into_compile_error();
+            let item_fn = (item_fn.sig.ident != "main").then_some(&item_fn);
+            quote! {
+                #err
+                fn main() {}
+                #item_fn
+            }
+        })
+}
@@ -1,49 +1,46 @@
 use proc_macro::TokenStream;
 use quote::quote;
 
+mod app;
+mod service;
+mod types;
+
+#[proc_macro_attribute]
+pub fn app(attr: TokenStream, item: TokenStream) -> TokenStream {
+    app::expand(attr.into(), item.into()).into()
+}
+
 #[proc_macro_attribute]
-pub fn app(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let item_fn = syn::parse_macro_input!(item as syn::ItemFn);
+pub fn service(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item_impl = syn::parse_macro_input!(item as syn::ItemImpl);
 
-    let item_fn_name = &item_fn.sig.ident;
+    service::expand(item_impl)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_attribute]
+pub fn method(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let error = syn::Error::new(
+        proc_macro2::Span::call_site(),
+        "`#[method]` can only be used inside a `#[genezio::service]` impl block",
+    )
+    .into_compile_error();
+
+    let item = proc_macro2::TokenStream::from(item);
 
     quote! {
-        pub fn main() {
-            let runtime = genezio::tokio::runtime::Builder::new_multi_thread()
-                    .enable_all()
-                    .build()
-                    .unwrap();
-
-            let app = #item_fn_name();
-
-            #[cfg(genezio_with_lambda)]
-            {
-                println!(
-                    "trap ready to receive events {}",
-                    std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis()
-                );
-                 runtime.block_on(async {
-                     genezio::lambda_http::run(app).await.unwrap();
-                 });
-            }
-
-            #[cfg(not(genezio_with_lambda))]
-            {
-                 runtime.block_on(async {
-                     let listener = genezio::tokio::net::TcpListener::bind("127.0.0.1:3000")
-                         .await
-                         .unwrap();
-                     println!("listening on {}", listener.local_addr().unwrap());
-
-                     genezio::axum::serve(listener, app).await.unwrap();
-                 });
-            }
-        }
-
-        #item_fn
+        #error
+        #item
     }
     .into()
 }
+
+#[proc_macro_derive(Type, attributes(serde))]
+pub fn derive_type(item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::DeriveInput);
+
+    types::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
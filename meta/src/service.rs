@@ -0,0 +1,275 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use serde_json::json;
+use syn::{
+    ext::IdentExt, spanned::Spanned, Attribute, FnArg, GenericArgument, Ident, ImplItem, ItemImpl,
+    Pat, PathArguments, ReturnType, Signature, Type,
+};
+
+use crate::types::type_ref;
+
+/// Marks the start of a service description inside the compiled binary.
+const METADATA_MARKER: &str = "\0genezio-service\0";
+
+struct Method {
+    ident: Ident,
+    has_receiver: bool,
+    is_async: bool,
+    is_fallible: bool,
+    args: Vec<(Ident, Type)>,
+    returns: Option<Type>,
+}
+
+fn is_method_attr(attr: &Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "method")
+}
+
+/// Returns the `T` of a `Result<T, E>` return type.
+fn result_ok_type(ty: &Type) -> Option<Option<&Type>> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return Some(None);
+    };
+
+    Some(args.args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }))
+}
+
+fn parse_method(sig: &Signature) -> syn::Result<Method> {
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            sig.generics.span(),
+            "service methods can't be generic",
+        ));
+    }
+
+    let mut has_receiver = false;
+    let mut args = vec![];
+
+    for input in &sig.inputs {
+        match input {
+            FnArg::Receiver(receiver) => {
+                if receiver.reference.is_none() || receiver.mutability.is_some() {
+                    return Err(syn::Error::new(
+                        receiver.span(),
+                        "service methods can only take `&self`",
+                    ));
+                }
+
+                has_receiver = true;
+            }
+            FnArg::Typed(arg) => {
+                let Pat::Ident(pat) = &*arg.pat else {
+                    return Err(syn::Error::new(
+                        arg.pat.span(),
+                        "service method parameters must be plain identifiers",
+                    ));
+                };
+
+                if let Type::Reference(_) = &*arg.ty {
+                    return Err(syn::Error::new(
+                        arg.ty.span(),
+                        "service method parameters must be owned types",
+                    ));
+                }
+
+                args.push((pat.ident.clone(), (*arg.ty).clone()));
+            }
+        }
+    }
+
+    let (is_fallible, returns) = match &sig.output {
+        ReturnType::Default => (false, None),
+        ReturnType::Type(_, ty) => match result_ok_type(ty) {
+            Some(ok) => (true, ok.cloned()),
+            None => (false, Some((**ty).clone())),
+        },
+    };
+
+    Ok(Method {
+        ident: sig.ident.clone(),
+        has_receiver,
+        is_async: sig.asyncness.is_some(),
+        is_fallible,
+        args,
+        returns,
+    })
+}
+
+fn render_metadata(name: &str, methods: &[Method]) -> String {
+    let methods = methods
+        .iter()
+        .map(|method| {
+            json!({
+                "name": method.ident.unraw().to_string(),
+                "params": method
+                    .args
+                    .iter()
+                    .map(|(ident, ty)| json!({ "name": ident.unraw().to_string(), "type": type_ref(ty) }))
+                    .collect::<Vec<_>>(),
+                "returns": method.returns.as_ref().map(type_ref),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let metadata = json!({
+        "name": name,
+        "methods": methods,
+    });
+
+    format!("{METADATA_MARKER}{metadata}\0")
+}
+
+fn render_dispatch(method: &Method) -> TokenStream {
+    let ident = &method.ident;
+    // `r#type` is called as `type`
+    let name = ident.unraw().to_string();
+
+    let arg_idents = method
+        .args
+        .iter()
+        .map(|(ident, _)| ident)
+        .collect::<Vec<_>>();
+    let arg_types = method.args.iter().map(|(_, ty)| ty);
+    let arg_names = method
+        .args
+        .iter()
+        .map(|(ident, _)| ident.unraw().to_string());
+
+    let parse_params = if method.args.is_empty() {
+        quote! {
+            genezio::rpc::no_params(params)?;
+        }
+    } else {
+        quote! {
+            let (#(#arg_idents,)*): (#(#arg_types,)*) =
+                genezio::rpc::params(params, &[#(#arg_names),*])?;
+        }
+    };
+
+    let call = if method.has_receiver {
+        quote! { self.#ident(#(#arg_idents),*) }
+    } else {
+        quote! { Self::#ident(#(#arg_idents),*) }
+    };
+
+    let call = if method.is_async {
+        quote! { #call.await }
+    } else {
+        call
+    };
+
+    let convert = if method.is_fallible {
+        quote! {
+            match result {
+                Ok(value) => genezio::rpc::result(value),
+                Err(err) => Err(genezio::rpc::Error::handler(err)),
+            }
+        }
+    } else {
+        quote! { genezio::rpc::result(result) }
+    };
+
+    quote! {
+        #name => Box::pin(async move {
+            #parse_params
+            let result = #call;
+            #convert
+        }),
+    }
+}
+
+pub fn expand(mut item: ItemImpl) -> syn::Result<TokenStream> {
+    if let Some((_, path, _)) = &item.trait_ {
+        return Err(syn::Error::new(
+            path.span(),
+            "`#[service]` can't be used on trait impls",
+        ));
+    }
+
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            item.generics.span(),
+            "`#[service]` can't be used on generic impls",
+        ));
+    }
+
+    let name = match &*item.self_ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    };
+
+    let Some(name) = name else {
+        return Err(syn::Error::new(
+            item.self_ty.span(),
+            "`#[service]` must be used on an impl block of a named type",
+        ));
+    };
+
+    let mut methods = vec![];
+    for impl_item in &mut item.items {
+        let ImplItem::Fn(item_fn) = impl_item else {
+            continue;
+        };
+
+        let attr_count = item_fn.attrs.len();
+        item_fn.attrs.retain(|attr| !is_method_attr(attr));
+        if item_fn.attrs.len() == attr_count {
+            continue;
+        }
+
+        methods.push(parse_method(&item_fn.sig)?);
+    }
+
+    let self_ty = &item.self_ty;
+    let metadata = render_metadata(&name, &methods);
+    let dispatch = methods.iter().map(render_dispatch);
+    let types = methods
+        .iter()
+        .flat_map(|method| method.args.iter().map(|(_, ty)| ty).chain(&method.returns));
+
+    Ok(quote! {
+        #item
+
+        impl genezio::rpc::Service for #self_ty {
+            const NAME: &'static str = #name;
+            const METADATA: &'static str = #metadata;
+
+            fn types(types: &mut Vec<&'static str>) {
+                #(<#types as genezio::rpc::Type>::collect(types);)*
+            }
+
+            #[allow(unused_variables)]
+            fn call(
+                self: std::sync::Arc<Self>,
+                method: &str,
+                params: genezio::serde_json::Value,
+            ) -> genezio::rpc::MethodFuture {
+                match method {
+                    #(#dispatch)*
+                    _ => {
+                        let method = format!("{}.{}", #name, method);
+                        Box::pin(async move { Err(genezio::rpc::Error::method_not_found(&method)) })
+                    }
+                }
+            }
+        }
+    })
+}
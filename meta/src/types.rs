@@ -0,0 +1,562 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use serde_json::{json, Value};
+use syn::{
+    ext::IdentExt, spanned::Spanned, Attribute, Data, DeriveInput, Fields, GenericArgument, LitStr,
+    PathArguments, Type,
+};
+
+/// Marks the start of a type description inside the compiled binary.
+const METADATA_MARKER: &str = "\0genezio-type\0";
+
+/// Describes a Rust type as written in the source, e.g. `{"name": "Vec", "args": [...]}`.
+pub fn type_ref(ty: &Type) -> Value {
+    match ty {
+        Type::Path(path) => {
+            let Some(segment) = path.path.segments.last() else {
+                return json!({ "name": "" });
+            };
+
+            let args = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => args
+                    .args
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        GenericArgument::Type(ty) => Some(type_ref(ty)),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+
+            json!({ "name": segment.ident.unraw().to_string(), "args": args })
+        }
+        Type::Tuple(tuple) => {
+            json!({ "tuple": tuple.elems.iter().map(type_ref).collect::<Vec<_>>() })
+        }
+        Type::Array(array) => json!({ "name": "Vec", "args": [type_ref(&array.elem)] }),
+        Type::Slice(slice) => json!({ "name": "Vec", "args": [type_ref(&slice.elem)] }),
+        Type::Reference(reference) => type_ref(&reference.elem),
+        Type::Paren(paren) => type_ref(&paren.elem),
+        Type::Group(group) => type_ref(&group.elem),
+        _ => json!({ "name": "" }),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(lit: &LitStr) -> syn::Result<Self> {
+        Ok(match lit.value().as_str() {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => return Err(syn::Error::new(lit.span(), "unknown rename rule")),
+        })
+    }
+
+    /// Same rules serde applies to `PascalCase` variant names.
+    fn apply_to_variant(self, name: &str) -> String {
+        match self {
+            Self::Pascal => name.to_owned(),
+            Self::Lower => name.to_ascii_lowercase(),
+            Self::Upper => name.to_ascii_uppercase(),
+            Self::Camel => lowercase_first(name),
+            Self::Snake => {
+                let mut snake = String::new();
+                for (i, ch) in name.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            }
+            Self::ScreamingSnake => Self::Snake.apply_to_variant(name).to_ascii_uppercase(),
+            Self::Kebab => Self::Snake.apply_to_variant(name).replace('_', "-"),
+            Self::ScreamingKebab => Self::ScreamingSnake
+                .apply_to_variant(name)
+                .replace('_', "-"),
+        }
+    }
+
+    /// Same rules serde applies to `snake_case` field names.
+    fn apply_to_field(self, name: &str) -> String {
+        match self {
+            Self::Lower | Self::Snake => name.to_owned(),
+            Self::Upper | Self::ScreamingSnake => name.to_ascii_uppercase(),
+            Self::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in name.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            Self::Camel => lowercase_first(&Self::Pascal.apply_to_field(name)),
+            Self::Kebab => name.replace('_', "-"),
+            Self::ScreamingKebab => Self::ScreamingSnake.apply_to_field(name).replace('_', "-"),
+        }
+    }
+}
+
+/// `name` with its first letter in lowercase, names like `_` have none.
+fn lowercase_first(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    tag: Option<String>,
+    content: Option<String>,
+    untagged: bool,
+    transparent: bool,
+    skip: bool,
+    optional: bool,
+    flatten: bool,
+}
+
+fn parse_serde_attrs(attrs: &[Attribute]) -> syn::Result<SerdeAttrs> {
+    let mut serde = SerdeAttrs::default();
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            let key = meta
+                .path
+                .get_ident()
+                .map(|ident| ident.to_string())
+                .unwrap_or_default();
+
+            match key.as_str() {
+                "rename" if meta.input.peek(syn::Token![=]) => {
+                    serde.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                }
+                "rename_all" if meta.input.peek(syn::Token![=]) => {
+                    serde.rename_all = Some(RenameRule::parse(&meta.value()?.parse()?)?);
+                }
+                "tag" => serde.tag = Some(meta.value()?.parse::<LitStr>()?.value()),
+                "content" => serde.content = Some(meta.value()?.parse::<LitStr>()?.value()),
+                "untagged" => serde.untagged = true,
+                "transparent" => serde.transparent = true,
+                "skip" => serde.skip = true,
+                // the field is only sent one way, so the other side can leave it out
+                "default" | "skip_serializing" | "skip_serializing_if" | "skip_deserializing" => {
+                    serde.optional = true
+                }
+                "flatten" => serde.flatten = true,
+                _ => {}
+            }
+
+            // skip over the values of the attributes we don't care about
+            if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let _content;
+                syn::parenthesized!(_content in meta.input);
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(serde)
+}
+
+fn render_fields(fields: &Fields, rename_all: Option<RenameRule>) -> syn::Result<Value> {
+    Ok(match fields {
+        Fields::Named(named) => {
+            let mut out = vec![];
+            for field in &named.named {
+                let serde = parse_serde_attrs(&field.attrs)?;
+                if serde.skip {
+                    continue;
+                }
+
+                let ident = field.ident.as_ref().unwrap().unraw().to_string();
+                let name = match (serde.rename, rename_all) {
+                    (Some(rename), _) => rename,
+                    (None, Some(rule)) => rule.apply_to_field(&ident),
+                    (None, None) => ident,
+                };
+
+                out.push(json!({
+                    "name": name,
+                    "type": type_ref(&field.ty),
+                    "optional": serde.optional,
+                    "flatten": serde.flatten,
+                }));
+            }
+
+            json!({ "kind": "struct", "fields": out })
+        }
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+            json!({ "kind": "alias", "type": type_ref(&unnamed.unnamed[0].ty) })
+        }
+        Fields::Unnamed(unnamed) => json!({
+            "kind": "tuple",
+            "items": unnamed.unnamed.iter().map(|field| type_ref(&field.ty)).collect::<Vec<_>>(),
+        }),
+        Fields::Unit => json!({ "kind": "unit" }),
+    })
+}
+
+fn render_metadata(input: &DeriveInput) -> syn::Result<String> {
+    let serde = parse_serde_attrs(&input.attrs)?;
+
+    let shape = match &input.data {
+        Data::Struct(data) if serde.transparent => {
+            let Some(field) = data.fields.iter().next() else {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "transparent structs need a field",
+                ));
+            };
+
+            json!({ "kind": "alias", "type": type_ref(&field.ty) })
+        }
+        Data::Struct(data) => render_fields(&data.fields, serde.rename_all)?,
+        Data::Enum(data) => {
+            let mut variants = vec![];
+            for variant in &data.variants {
+                let variant_serde = parse_serde_attrs(&variant.attrs)?;
+                if variant_serde.skip {
+                    continue;
+                }
+
+                let ident = variant.ident.unraw().to_string();
+                let name = match (variant_serde.rename, serde.rename_all) {
+                    (Some(rename), _) => rename,
+                    (None, Some(rule)) => rule.apply_to_variant(&ident),
+                    (None, None) => ident,
+                };
+
+                variants.push(json!({
+                    "name": name,
+                    "shape": render_fields(&variant.fields, variant_serde.rename_all)?,
+                }));
+            }
+
+            json!({
+                "kind": "enum",
+                "tag": serde.tag,
+                "content": serde.content,
+                "untagged": serde.untagged,
+                "variants": variants,
+            })
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new(
+                input.span(),
+                "`#[derive(Type)]` doesn't support unions",
+            ))
+        }
+    };
+
+    let generics = input
+        .generics
+        .type_params()
+        .map(|param| param.ident.to_string())
+        .collect::<Vec<_>>();
+
+    let metadata = json!({
+        "name": input.ident.unraw().to_string(),
+        "generics": generics,
+        "shape": shape,
+    });
+
+    Ok(format!("{METADATA_MARKER}{metadata}\0"))
+}
+
+fn is_skipped(field: &syn::Field) -> bool {
+    parse_serde_attrs(&field.attrs).is_ok_and(|serde| serde.skip)
+}
+
+fn field_types(input: &DeriveInput) -> Vec<&Type> {
+    let fields: Vec<&syn::Field> = match &input.data {
+        Data::Struct(data) => data.fields.iter().collect(),
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .filter(|variant| !parse_serde_attrs(&variant.attrs).is_ok_and(|serde| serde.skip))
+            .flat_map(|variant| variant.fields.iter())
+            .collect(),
+        Data::Union(_) => vec![],
+    };
+
+    fields
+        .into_iter()
+        .filter(|field| !is_skipped(field))
+        .map(|field| &field.ty)
+        .collect()
+}
+
+pub fn expand(mut input: DeriveInput) -> syn::Result<TokenStream> {
+    let metadata = render_metadata(&input)?;
+    let field_types = field_types(&input).into_iter().cloned().collect::<Vec<_>>();
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(genezio::rpc::Type));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics genezio::rpc::Type for #ident #ty_generics #where_clause {
+            fn collect(types: &mut Vec<&'static str>) {
+                const METADATA: &str = #metadata;
+                if types.contains(&METADATA) {
+                    return;
+                }
+
+                types.push(METADATA);
+                #(<#field_types as genezio::rpc::Type>::collect(types);)*
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn metadata(input: DeriveInput) -> Value {
+        let metadata = render_metadata(&input).unwrap();
+        let json = metadata
+            .strip_prefix(METADATA_MARKER)
+            .unwrap()
+            .trim_end_matches('\0');
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn field_names(shape: &Value) -> Vec<&str> {
+        shape["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field["name"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn struct_fields() {
+        let metadata = metadata(parse_quote! {
+            #[serde(rename_all = "camelCase")]
+            struct User {
+                user_id: u64,
+                #[serde(rename = "mail")]
+                email_address: String,
+                #[serde(default)]
+                nick_name: Option<String>,
+                #[serde(skip)]
+                cache: Vec<u8>,
+                #[serde(skip_serializing)]
+                password: String,
+                #[serde(skip_deserializing)]
+                created_at: u64,
+                #[serde(flatten)]
+                extra: Extra,
+            }
+        });
+
+        let shape = &metadata["shape"];
+        assert_eq!(metadata["name"], "User");
+        assert_eq!(shape["kind"], "struct");
+        assert_eq!(
+            field_names(shape),
+            [
+                "userId",
+                "mail",
+                "nickName",
+                "password",
+                "createdAt",
+                "extra"
+            ]
+        );
+
+        let optional = shape["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field["optional"].as_bool().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(optional, [false, false, true, true, true, false]);
+        assert_eq!(shape["fields"][5]["flatten"], true);
+        assert_eq!(
+            shape["fields"][2]["type"],
+            json!({ "name": "Option", "args": [{ "name": "String", "args": [] }] })
+        );
+    }
+
+    #[test]
+    fn rename_rules() {
+        let cases = [
+            ("lowercase", "httpserver", "user_id"),
+            ("UPPERCASE", "HTTPSERVER", "USER_ID"),
+            ("PascalCase", "HttpServer", "UserId"),
+            ("camelCase", "httpServer", "userId"),
+            ("snake_case", "http_server", "user_id"),
+            ("SCREAMING_SNAKE_CASE", "HTTP_SERVER", "USER_ID"),
+            ("kebab-case", "http-server", "user-id"),
+            ("SCREAMING-KEBAB-CASE", "HTTP-SERVER", "USER-ID"),
+        ];
+
+        for (rule, variant, field) in cases {
+            let rule =
+                RenameRule::parse(&LitStr::new(rule, proc_macro2::Span::call_site())).unwrap();
+            assert_eq!(rule.apply_to_variant("HttpServer"), variant);
+            assert_eq!(rule.apply_to_field("user_id"), field);
+
+            // names that are only underscores, or nothing at all, don't panic
+            rule.apply_to_field("_");
+            rule.apply_to_field("__");
+            rule.apply_to_field("");
+        }
+
+        let camel =
+            RenameRule::parse(&LitStr::new("camelCase", proc_macro2::Span::call_site())).unwrap();
+        assert_eq!(camel.apply_to_field("__"), "");
+        assert_eq!(camel.apply_to_field("_private"), "private");
+    }
+
+    #[test]
+    fn raw_identifiers_are_unraw() {
+        let raw = metadata(parse_quote! {
+            #[serde(rename_all = "camelCase")]
+            struct r#Match {
+                r#type: String,
+                r#ref_count: u32,
+                r#in: r#Match,
+            }
+        });
+
+        assert_eq!(raw["name"], "Match");
+        assert_eq!(field_names(&raw["shape"]), ["type", "refCount", "in"]);
+        assert_eq!(raw["shape"]["fields"][2]["type"]["name"], "Match");
+
+        let keyword = metadata(parse_quote! {
+            enum Keyword {
+                r#async,
+                r#Type(u8),
+            }
+        });
+        let names = keyword["shape"]["variants"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| variant["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["async", "Type"]);
+    }
+
+    #[test]
+    fn tagged_enums() {
+        let metadata = metadata(parse_quote! {
+            #[serde(tag = "type", content = "data", rename_all = "snake_case")]
+            enum Event {
+                UserCreated { user_id: u64 },
+                #[serde(rename = "gone")]
+                UserDeleted(u64),
+                Ping,
+                #[serde(skip)]
+                Internal(String),
+            }
+        });
+
+        let shape = &metadata["shape"];
+        assert_eq!(shape["kind"], "enum");
+        assert_eq!(shape["tag"], "type");
+        assert_eq!(shape["content"], "data");
+        assert_eq!(shape["untagged"], false);
+
+        let variants = shape["variants"].as_array().unwrap();
+        let names = variants
+            .iter()
+            .map(|variant| variant["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["user_created", "gone", "ping"]);
+        assert_eq!(variants[0]["shape"]["kind"], "struct");
+        assert_eq!(variants[1]["shape"]["kind"], "alias");
+        assert_eq!(variants[2]["shape"]["kind"], "unit");
+    }
+
+    #[test]
+    fn untagged_enums() {
+        let metadata = metadata(parse_quote! {
+            #[serde(untagged)]
+            enum Id {
+                Number(u64),
+                Pair(u32, String),
+            }
+        });
+
+        let shape = &metadata["shape"];
+        assert_eq!(shape["untagged"], true);
+        assert_eq!(shape["tag"], Value::Null);
+        assert_eq!(shape["variants"][1]["shape"]["kind"], "tuple");
+    }
+
+    #[test]
+    fn transparent_structs() {
+        let metadata = metadata(parse_quote! {
+            #[serde(transparent)]
+            struct UserId<T> {
+                id: T,
+            }
+        });
+
+        assert_eq!(metadata["generics"], json!(["T"]));
+        assert_eq!(
+            metadata["shape"],
+            json!({ "kind": "alias", "type": { "name": "T", "args": [] } })
+        );
+    }
+
+    #[test]
+    fn skipped_fields_are_not_collected() {
+        let input: DeriveInput = parse_quote! {
+            struct Session {
+                user: User,
+                #[serde(skip)]
+                cache: Cache,
+                #[serde(skip_serializing)]
+                token: Token,
+            }
+        };
+
+        let types = field_types(&input)
+            .into_iter()
+            .map(|ty| quote!(#ty).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["User", "Token"]);
+    }
+}
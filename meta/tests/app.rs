@@ -0,0 +1,5 @@
+#[test]
+fn app_diagnostics() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/app/*.rs");
+}
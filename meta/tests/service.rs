@@ -0,0 +1,122 @@
+use genezio::{
+    rpc::{Error, Rpc, Service},
+    serde_json::{json, Value},
+    service,
+};
+
+struct Calc;
+
+#[service]
+impl Calc {
+    #[method]
+    async fn add(a: i32, b: Option<i32>) -> i32 {
+        a + b.unwrap_or_default()
+    }
+
+    #[method]
+    fn zero() -> i32 {
+        0
+    }
+
+    #[method]
+    fn r#type(r#ref: String) -> String {
+        r#ref
+    }
+
+    #[method]
+    fn file_name(path: std::path::PathBuf) -> Option<String> {
+        Some(path.file_name()?.to_string_lossy().into_owned())
+    }
+}
+
+fn call(method: &str, params: Value) -> Result<Value, Error> {
+    let rpc = Rpc::new().service(Calc);
+    genezio::tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(rpc.call(method, params))
+}
+
+#[test]
+fn positional_params() {
+    assert_eq!(call("Calc.add", json!([1, 2])).unwrap(), json!(3));
+    // trailing options can be left out, like with named params
+    assert_eq!(call("Calc.add", json!([1])).unwrap(), json!(1));
+    assert_eq!(
+        call("Calc.add", json!([])).unwrap_err().code,
+        Error::INVALID_PARAMS
+    );
+    assert_eq!(
+        call("Calc.add", json!([1, 2, 3])).unwrap_err().code,
+        Error::INVALID_PARAMS
+    );
+}
+
+#[test]
+fn named_params() {
+    assert_eq!(call("Calc.add", json!({"a": 1, "b": 2})).unwrap(), json!(3));
+    assert_eq!(call("Calc.add", json!({"a": 1})).unwrap(), json!(1));
+    assert_eq!(
+        call("Calc.add", json!({"b": 1})).unwrap_err().code,
+        Error::INVALID_PARAMS
+    );
+    assert_eq!(
+        call("Calc.add", json!({"a": 1, "c": 2})).unwrap_err().code,
+        Error::INVALID_PARAMS
+    );
+}
+
+#[test]
+fn methods_without_arguments_take_no_params() {
+    assert_eq!(call("Calc.zero", Value::Null).unwrap(), json!(0));
+    assert_eq!(call("Calc.zero", json!([])).unwrap(), json!(0));
+    assert_eq!(call("Calc.zero", json!({})).unwrap(), json!(0));
+    assert_eq!(
+        call("Calc.zero", json!([1])).unwrap_err().code,
+        Error::INVALID_PARAMS
+    );
+    assert_eq!(
+        call("Calc.zero", json!({"a": 1})).unwrap_err().code,
+        Error::INVALID_PARAMS
+    );
+}
+
+#[test]
+fn std_types_without_metadata() {
+    assert_eq!(call("Calc.file_name", json!(["/a/b.txt"])).unwrap(), json!("b.txt"));
+}
+
+#[test]
+fn raw_identifiers_are_unraw() {
+    assert_eq!(call("Calc.type", json!({"ref": "a"})).unwrap(), json!("a"));
+    assert_eq!(
+        call("Calc.r#type", json!(["a"])).unwrap_err().code,
+        Error::METHOD_NOT_FOUND
+    );
+
+    let metadata = Calc::METADATA.trim_matches('\0');
+    let metadata: Value =
+        genezio::serde_json::from_str(metadata.strip_prefix("genezio-service\0").unwrap()).unwrap();
+    assert_eq!(metadata["methods"][2]["name"], "type");
+    assert_eq!(metadata["methods"][2]["params"][0]["name"], "ref");
+}
+
+mod other {
+    use genezio::service;
+
+    pub struct Calc;
+
+    #[service]
+    impl Calc {
+        #[method]
+        fn one() -> i32 {
+            1
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "two services are named `Calc`")]
+fn services_with_the_same_name_are_rejected() {
+    let _ = Rpc::new().service(Calc).service(other::Calc);
+}
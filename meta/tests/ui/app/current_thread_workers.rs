@@ -0,0 +1,6 @@
+use genezio::axum::Router;
+
+#[genezio::app(flavor = "current_thread", worker_threads = 2)]
+fn app() -> Router {
+    Router::new()
+}
@@ -0,0 +1,7 @@
+use genezio::axum::Router;
+
+#[genezio::app]
+fn app<S>(state: S) -> Router {
+    let _ = state;
+    Router::new()
+}
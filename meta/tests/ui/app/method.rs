@@ -0,0 +1,12 @@
+use genezio::axum::Router;
+
+struct Server;
+
+impl Server {
+    #[genezio::app]
+    fn app(&self) -> Router {
+        Router::new()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[genezio::app]
+fn main() -> genezio::axum::Router {
+    genezio::axum::Router::new()
+}
@@ -0,0 +1,2 @@
+#[genezio::app]
+fn app() {}
@@ -0,0 +1,6 @@
+use genezio::axum::Router;
+
+#[genezio::app(prot = 8080)]
+fn app() -> Router {
+    Router::new()
+}
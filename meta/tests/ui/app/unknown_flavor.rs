@@ -0,0 +1,6 @@
+use genezio::axum::Router;
+
+#[genezio::app(flavor = "single_thread")]
+fn app() -> Router {
+    Router::new()
+}
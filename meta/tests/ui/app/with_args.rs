@@ -0,0 +1,6 @@
+use genezio::axum::Router;
+
+#[genezio::app]
+fn app(prefix: &str) -> Router {
+    Router::new().nest(prefix, Router::new())
+}
@@ -0,0 +1,4 @@
+#[genezio::app]
+fn app() -> String {
+    String::new()
+}